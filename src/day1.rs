@@ -1,10 +1,14 @@
+// Blank lines separate the elves, so this can't use `clean_lines`
 fn parse(input: &str) -> impl Iterator<Item = i32> {
     input
         .lines()
         .map(|l| l.trim())
-        .fold(vec![vec![]], |mut v, line| {
+        .fold(vec![vec![]], |mut v: Vec<Vec<i32>>, line| {
             if line.is_empty() {
-                v.push(vec![]);
+                // Any run of blank lines is a single separator
+                if !v.last().unwrap().is_empty() {
+                    v.push(vec![]);
+                }
             } else {
                 let cals = line.parse::<i32>().unwrap();
                 v.last_mut().unwrap().push(cals);
@@ -12,8 +16,8 @@ fn parse(input: &str) -> impl Iterator<Item = i32> {
             v
         })
         .into_iter()
+        .filter(|v| !v.is_empty())
         .map(|v| v.into_iter().sum())
-        .filter(|&cals| cals != 0)
 }
 
 pub(crate) fn solve(input: &str) -> i32 {
@@ -60,4 +64,17 @@ mod tests {
         );
         assert_eq!(result, 500);
     }
+
+    #[test]
+    fn test_blank_runs() {
+        let elves = parse("\n\n100\n200\n\n\n\n300\n\n").collect::<Vec<_>>();
+        assert_eq!(elves, vec![300, 300]);
+    }
+
+    #[test]
+    fn test_zero_elf() {
+        let elves = parse("0\n\n100\n\n0\n0").collect::<Vec<_>>();
+        assert_eq!(elves, vec![0, 100, 0]);
+        assert_eq!(solve("0\n0\n\n0"), 0);
+    }
 }
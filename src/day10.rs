@@ -2,25 +2,29 @@ use std::iter::{once, repeat};
 
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 #[derive(Clone, Copy)]
 enum Instruction {
     Addx(isize),
+    Mulx(isize),
     Noop,
 }
 
+// Extended mode also accepts `mulx N`, which multiplies X
+fn parse_with(input: &str, extended: bool) -> impl Iterator<Item = Instruction> + '_ {
+    clean_lines(input).map(move |l| {
+        match l.split_ascii_whitespace().collect_vec()[..] {
+            ["noop"] => Instruction::Noop,
+            ["addx", num] => Instruction::Addx(num.parse::<isize>().unwrap()),
+            ["mulx", num] if extended => Instruction::Mulx(num.parse::<isize>().unwrap()),
+            _ => panic!("Unexpected instruction: {l}"),
+        }
+    })
+}
+
 fn parse(input: &str) -> impl Iterator<Item = Instruction> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .map(|l| {
-            if l.starts_with("noop") {
-                Instruction::Noop
-            } else {
-                let (_, num) = l.split_ascii_whitespace().collect_tuple().unwrap();
-                Instruction::Addx(num.parse::<isize>().unwrap())
-            }
-        })
+    parse_with(input, false)
 }
 
 fn x_reg(instructions: impl Iterator<Item = Instruction>) -> impl Iterator<Item = isize> {
@@ -30,7 +34,7 @@ fn x_reg(instructions: impl Iterator<Item = Instruction>) -> impl Iterator<Item
             repeat(Instruction::Noop)
                 .take(match instr {
                     Instruction::Noop => 0,
-                    Instruction::Addx(_) => 1,
+                    Instruction::Addx(_) | Instruction::Mulx(_) => 1,
                 })
                 .chain(once(instr))
         })
@@ -41,28 +45,35 @@ fn x_reg(instructions: impl Iterator<Item = Instruction>) -> impl Iterator<Item
                 x += add;
                 x_pre_add
             }
+            Instruction::Mulx(mul) => {
+                let x_pre_mul = x;
+                x *= mul;
+                x_pre_mul
+            }
         })
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    x_reg(parse(input))
-        .enumerate()
-        .fold(0, |mut strength, (i, x)| {
-            let i = i as isize + 1;
-            if (i - 20) % 40 == 0 {
-                strength += i * x;
-            }
-            strength
-        }) as usize
+// The CRT output already is the rendering
+pub(crate) fn visualize(input: &str) -> String {
+    solve_2(input)
 }
 
-pub(crate) fn solve_2(input: &str) -> String {
-    x_reg(parse(input))
-        .enumerate()
+fn signal_strength(xs: impl Iterator<Item = isize>) -> isize {
+    xs.enumerate().fold(0, |mut strength, (i, x)| {
+        let i = i as isize + 1;
+        if (i - 20) % 40 == 0 {
+            strength += i * x;
+        }
+        strength
+    })
+}
+
+fn render_crt(xs: impl Iterator<Item = isize>, width: usize) -> String {
+    xs.enumerate()
         .flat_map(|(i, x)| {
             once("\n")
-                .take((i % 40 == 0) as usize)
-                .chain(if (i as isize % 40 - x).abs() <= 1 {
+                .take((i % width == 0) as usize)
+                .chain(if ((i % width) as isize - x).abs() <= 1 {
                     once("#")
                 } else {
                     once(" ")
@@ -70,3 +81,239 @@ pub(crate) fn solve_2(input: &str) -> String {
         })
         .collect()
 }
+
+pub(crate) fn solve(input: &str) -> isize {
+    signal_strength(x_reg(parse(input)))
+}
+
+// The CRT image for a screen `width` pixels wide, one row per `width` cycles
+pub(crate) fn render_crt_sized(input: &str, width: usize) -> String {
+    render_crt(x_reg(parse(input)), width)
+}
+
+pub(crate) fn solve_2(input: &str) -> String {
+    render_crt_sized(input, 40)
+}
+
+// Both answers from a single pass over the X register trace
+fn run_both(input: &str) -> (isize, String) {
+    let xs = x_reg(parse(input)).collect_vec();
+    (signal_strength(xs.iter().copied()), render_crt(xs.into_iter(), 40))
+}
+
+pub(crate) fn report(input: &str) -> String {
+    let (strength, crt) = run_both(input);
+    format!("Signal strength {strength}, screen:{crt}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "
+        addx 15
+        addx -11
+        addx 6
+        addx -3
+        addx 5
+        addx -1
+        addx -8
+        addx 13
+        addx 4
+        noop
+        addx -1
+        addx 5
+        addx -1
+        addx 5
+        addx -1
+        addx 5
+        addx -1
+        addx 5
+        addx -1
+        addx -35
+        addx 1
+        addx 24
+        addx -19
+        addx 1
+        addx 16
+        addx -11
+        noop
+        noop
+        addx 21
+        addx -15
+        noop
+        noop
+        addx -3
+        addx 9
+        addx 1
+        addx -3
+        addx 8
+        addx 1
+        addx 5
+        noop
+        noop
+        noop
+        noop
+        noop
+        addx -36
+        noop
+        addx 1
+        addx 7
+        noop
+        noop
+        noop
+        addx 2
+        addx 6
+        noop
+        noop
+        noop
+        noop
+        noop
+        addx 1
+        noop
+        noop
+        addx 7
+        addx 1
+        noop
+        addx -13
+        addx 13
+        addx 7
+        noop
+        addx 1
+        addx -33
+        noop
+        noop
+        noop
+        addx 2
+        noop
+        noop
+        noop
+        addx 8
+        noop
+        addx -1
+        addx 2
+        addx 1
+        noop
+        addx 17
+        addx -9
+        addx 1
+        addx 1
+        addx -3
+        addx 11
+        noop
+        noop
+        addx 1
+        noop
+        addx 1
+        noop
+        noop
+        addx -13
+        addx -19
+        addx 1
+        addx 3
+        addx 26
+        addx -30
+        addx 12
+        addx -1
+        addx 3
+        addx 1
+        noop
+        noop
+        noop
+        addx -9
+        addx 18
+        addx 1
+        addx 2
+        noop
+        noop
+        addx 9
+        noop
+        noop
+        noop
+        addx -1
+        addx 2
+        addx -37
+        addx 1
+        addx 3
+        noop
+        addx 15
+        addx -21
+        addx 22
+        addx -6
+        addx 1
+        noop
+        addx 2
+        addx 1
+        noop
+        addx -10
+        noop
+        noop
+        addx 20
+        addx 1
+        addx 2
+        addx 2
+        addx -6
+        addx -11
+        noop
+        noop
+        noop
+    ";
+
+    #[test]
+    fn test_mulx() {
+        let program = "
+            addx 2
+            mulx 3
+            noop
+            mulx -1
+            noop
+        ";
+        let xs = x_reg(parse_with(program, true)).collect_vec();
+        assert_eq!(xs, vec![1, 1, 3, 3, 9, 9, 9, -9]);
+    }
+
+    #[test]
+    fn test_negative_strength() {
+        // X is -4 by the 20th cycle
+        let program = once("addx -5").chain(["noop"; 18]).join("\n");
+        assert_eq!(solve(&program), 20 * -4);
+    }
+
+    #[test]
+    fn test_run_both() {
+        let (strength, crt) = run_both(EXAMPLE);
+        assert_eq!(strength, 13140);
+        assert_eq!(
+            crt.replace(' ', "."),
+            "
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######....."
+        );
+        assert_eq!((strength, crt), (solve(EXAMPLE), solve_2(EXAMPLE)));
+        assert!(report(EXAMPLE).starts_with("Signal strength 13140, screen:\n##  ##"));
+    }
+
+    #[test]
+    fn test_render_crt_sized() {
+        // X moves to 6 after the second cycle, so the sprite repeats on each 20-pixel row
+        let program = once("addx 5").chain(["noop"; 38]).join("\n");
+        assert_eq!(
+            render_crt_sized(&program, 20).replace(' ', "."),
+            "
+##...###............
+.....###............"
+        );
+        assert_eq!(render_crt_sized(&program, 40).lines().count(), 2);
+        assert_eq!(render_crt_sized(EXAMPLE, 40), solve_2(EXAMPLE));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mulx_needs_extended() {
+        parse("mulx 3").for_each(drop);
+    }
+}
@@ -2,35 +2,42 @@ use std::iter::Peekable;
 
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    Add(isize),
+    Mul(isize),
+    Double,
+    Square,
+}
+
+impl Operation {
+    fn apply(self, old: isize) -> isize {
+        match self {
+            Operation::Add(num) => old + num,
+            Operation::Mul(num) => old * num,
+            Operation::Double => old + old,
+            Operation::Square => old * old,
+        }
+    }
+}
+
+#[derive(Clone)]
 struct Monkey {
     items: Vec<isize>,
-    operation: Box<dyn Fn(isize) -> isize>,
+    operation: Operation,
     test: isize,
     on_true: isize,
     on_false: isize,
 }
 
 impl Monkey {
-    fn compute(&mut self) -> Vec<(isize, isize)> {
+    fn compute(&mut self, divisor: isize) -> Vec<(isize, isize)> {
         self.items
             .drain(..)
             .map(|item| {
-                let new_item = (self.operation)(item) / 3;
-                let destination = if new_item % self.test == 0 {
-                    self.on_true
-                } else {
-                    self.on_false
-                };
-                (destination, new_item)
-            })
-            .collect()
-    }
-
-    fn compute_2(&mut self) -> Vec<(isize, isize)> {
-        self.items
-            .drain(..)
-            .map(|item| {
-                let new_item = (self.operation)(item);
+                let new_item = self.operation.apply(item) / divisor;
                 let destination = if new_item % self.test == 0 {
                     self.on_true
                 } else {
@@ -66,20 +73,14 @@ impl<'a, T: Iterator<Item = &'a str>> Iterator for MonkeyIterator<T> {
             .map(|item| item.parse::<isize>().unwrap())
             .collect_vec();
 
-        let operation: Box<dyn Fn(isize) -> isize> = match &strip("Operation: new = old ")
+        let operation = match &strip("Operation: new = old ")
             .split_ascii_whitespace()
             .collect_vec()[..]
         {
-            &["+", "old"] => Box::new(|old: isize| old + old),
-            &["*", "old"] => Box::new(|old: isize| old * old),
-            &["+", num] => {
-                let num = num.parse::<isize>().unwrap();
-                Box::new(move |old: isize| old + num)
-            }
-            &["*", num] => {
-                let num = num.parse::<isize>().unwrap();
-                Box::new(move |old: isize| old * num)
-            }
+            &["+", "old"] => Operation::Double,
+            &["*", "old"] => Operation::Square,
+            &["+", num] => Operation::Add(num.parse::<isize>().unwrap()),
+            &["*", num] => Operation::Mul(num.parse::<isize>().unwrap()),
             _ => panic!("Unexpected operation"),
         };
 
@@ -101,20 +102,46 @@ impl<'a, T: Iterator<Item = &'a str>> Iterator for MonkeyIterator<T> {
 
 fn parse(input: &str) -> impl Iterator<Item = Monkey> + '_ {
     MonkeyIterator {
-        lines: input
-            .lines()
-            .map(|l| l.trim())
-            .filter(|l| !l.is_empty())
+        lines: clean_lines(input)
             .peekable(),
     }
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    let mut monkeys = parse(input).collect_vec();
+// (monkey, on_true, on_false) edges, in monkey order
+fn throw_graph(input: &str) -> Vec<(usize, usize, usize)> {
+    parse(input)
+        .enumerate()
+        .map(|(i, m)| (i, m.on_true as usize, m.on_false as usize))
+        .collect()
+}
+
+// The throw graph in Graphviz DOT, e.g. for `dot -Tsvg`
+pub(crate) fn visualize(input: &str) -> String {
+    let edges = throw_graph(input)
+        .into_iter()
+        .flat_map(|(monkey, on_true, on_false)| {
+            [
+                format!("    {monkey} -> {on_true} [label=\"true\"];"),
+                format!("    {monkey} -> {on_false} [label=\"false\"];"),
+            ]
+        })
+        .join("\n");
+    format!("digraph monkeys {{\n{edges}\n}}")
+}
+
+// Worry levels are divided by `divisor` after each inspection; 1 means no relief
+fn monkey_business(input: &str, rounds: usize, divisor: isize) -> usize {
+    simulate(parse(input).collect_vec(), rounds, divisor)
+}
+
+fn simulate(mut monkeys: Vec<Monkey>, rounds: usize, divisor: isize) -> usize {
     let mut counts = vec![0; monkeys.len()];
-    for _ in 0..20 {
+    // Reducing modulo the tests is only sound when nothing is divided
+    let modulus: Option<isize> = (divisor == 1).then(|| monkeys.iter().map(|m| m.test).product());
+    for _ in 0..rounds {
         for i in 0..monkeys.len() {
-            for (dest, item) in monkeys[i].compute() {
+            for (dest, item) in monkeys[i].compute(divisor) {
+                let item = modulus.map_or(item, |modulus| item % modulus);
                 monkeys[dest as usize].items.push(item);
                 counts[i] += 1;
             }
@@ -124,21 +151,26 @@ pub(crate) fn solve(input: &str) -> usize {
     counts.iter().rev().take(2).product()
 }
 
+// Both parts from a single parse
+fn run_both(input: &str) -> (usize, usize) {
+    let monkeys = parse(input).collect_vec();
+    (
+        simulate(monkeys.clone(), 20, 3),
+        simulate(monkeys, 10000, 1),
+    )
+}
+
+pub(crate) fn report(input: &str) -> String {
+    let (relieved, worried) = run_both(input);
+    format!("Monkey business {relieved} with relief, {worried} without")
+}
+
+pub(crate) fn solve(input: &str) -> usize {
+    monkey_business(input, 20, 3)
+}
+
 pub(crate) fn solve_2(input: &str) -> usize {
-    let mut monkeys = parse(input).collect_vec();
-    let mut counts = vec![0; monkeys.len()];
-    let modulus: isize = monkeys.iter().map(|m| m.test).product();
-    for _ in 0..10000 {
-        for i in 0..monkeys.len() {
-            for (dest, item) in monkeys[i].compute_2() {
-                let item = item % modulus;
-                monkeys[dest as usize].items.push(item);
-                counts[i] += 1;
-            }
-        }
-    }
-    counts.sort();
-    counts.iter().rev().take(2).product()
+    monkey_business(input, 10000, 1)
 }
 
 #[cfg(test)]
@@ -161,6 +193,7 @@ mod tests {
         .unwrap();
 
         assert_eq!(monkey.items, vec![79, 60, 97]);
+        assert_eq!(monkey.operation, Operation::Square);
         assert_eq!(monkey.test, 13);
         assert_eq!(monkey.on_true, 1);
         assert_eq!(monkey.on_false, 3);
@@ -181,7 +214,7 @@ mod tests {
         .next()
         .unwrap();
 
-        assert_eq!(monkey.compute(), vec![(3, 500), (3, 620)]);
+        assert_eq!(monkey.compute(3), vec![(3, 500), (3, 620)]);
     }
 
     const EXAMPLE: &str = "
@@ -214,6 +247,20 @@ mod tests {
             If false: throw to monkey 1
     ";
 
+    #[test]
+    fn test_throw_graph() {
+        assert_eq!(
+            throw_graph(EXAMPLE),
+            vec![(0, 2, 3), (1, 2, 0), (2, 1, 3), (3, 0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_relief_divisor() {
+        assert_eq!(monkey_business(EXAMPLE, 1, 2), 4 * 5);
+        assert_eq!(monkey_business(EXAMPLE, 1, 1), 4 * 6);
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(solve(EXAMPLE), 10605);
@@ -223,4 +270,22 @@ mod tests {
     fn test_solve_2() {
         assert_eq!(solve_2(EXAMPLE), 2713310158);
     }
+
+    #[test]
+    fn test_visualize() {
+        let dot = visualize(EXAMPLE);
+        assert!(dot.starts_with("digraph monkeys {\n    0 -> 2 [label=\"true\"];\n"));
+        assert!(dot.ends_with("    3 -> 1 [label=\"false\"];\n}"));
+        assert_eq!(dot.lines().count(), 10);
+    }
+
+    #[test]
+    fn test_run_both() {
+        assert_eq!(run_both(EXAMPLE), (solve(EXAMPLE), solve_2(EXAMPLE)));
+        assert_eq!(run_both(EXAMPLE), (10605, 2713310158));
+        assert_eq!(
+            report(EXAMPLE),
+            "Monkey business 10605 with relief, 2713310158 without"
+        );
+    }
 }
@@ -1,107 +1,80 @@
-use std::collections::HashSet;
+use crate::utils::{self, dijkstra};
 
 struct Grid {
-    cells: Vec<Vec<isize>>,
+    cells: utils::Grid<isize>,
     start: (usize, usize),
-    end: (usize, usize),
-    size: (usize, usize),
+    // Every `E`; the search starts from all of them, so the nearest one wins
+    ends: Vec<(usize, usize)>,
 }
 
 impl Grid {
     fn new(input: &str) -> Self {
-        let mut cells = vec![];
         let mut start = None;
-        let mut end = None;
-        let lines = input.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
-        for (y, line) in lines.enumerate() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
+        let mut ends = Vec::new();
+        let cells = utils::Grid::from_chars(input, |pos, mut c| {
+            if c == 'S' {
+                start = Some(pos);
+                c = 'a';
+            } else if c == 'E' {
+                ends.push(pos);
+                c = 'z';
             }
-            let mut row_cells = vec![];
-            for (x, mut c) in line.chars().enumerate() {
-                if c == 'S' {
-                    start = Some((x, y));
-                    c = 'a';
-                } else if c == 'E' {
-                    end = Some((x, y));
-                    c = 'z';
-                }
-                row_cells.push(c as isize - 'a' as isize);
-            }
-            cells.push(row_cells);
-        }
-
-        let x_dim = cells.first().unwrap().len();
-        let y_dim = cells.len();
+            c as isize - 'a' as isize
+        });
 
         Self {
             cells,
             start: start.unwrap(),
-            end: end.unwrap(),
-            size: (x_dim, y_dim),
+            ends,
         }
     }
-}
 
-struct BFS<'a> {
-    grid: &'a Grid,
-    queue: Vec<((usize, usize), usize)>,
-    seen: HashSet<(usize, usize)>,
-}
-
-impl<'a> BFS<'a> {
-    fn new(grid: &'a Grid) -> Self {
+    // For grids without `S` and `E` markers
+    #[cfg(test)]
+    fn from_heights(
+        heights: Vec<Vec<isize>>,
+        start: (usize, usize),
+        ends: Vec<(usize, usize)>,
+    ) -> Self {
         Self {
-            grid,
-            queue: vec![(grid.end, 0)],
-            seen: HashSet::new(),
+            cells: utils::Grid::new(heights),
+            start,
+            ends,
         }
     }
-}
 
-impl Iterator for BFS<'_> {
-    type Item = ((usize, usize), usize);
+    fn neighbors(&self, pos: (usize, usize)) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+        // Search backwards from the end, so each step may climb down at most one
+        self.cells
+            .neighbors4(pos)
+            .filter(move |&next| self.cells[pos] <= self.cells[next] + 1)
+            .map(|p| (p, 1))
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some((pos, steps)) = self.queue.first().copied() {
-            self.queue.remove(0);
-            if !self.seen.insert(pos) {
-                continue;
+    fn shortest_path(&self, mut is_goal: impl FnMut(&(usize, usize)) -> bool) -> usize {
+        // `None` is a virtual node one step before every end, so they're all seeded
+        let neighbors = |&pos: &Option<(usize, usize)>| -> Vec<_> {
+            match pos {
+                None => self.ends.iter().map(|&end| (Some(end), 0)).collect(),
+                Some(pos) => self
+                    .neighbors(pos)
+                    .map(|(p, cost)| (Some(p), cost))
+                    .collect(),
             }
-            self.queue.extend(
-                [
-                    (pos.0, pos.1 + 1),
-                    (pos.0, pos.1.wrapping_sub(1)),
-                    (pos.0 + 1, pos.1),
-                    (pos.0.wrapping_sub(1), pos.1),
-                ]
-                .into_iter()
-                .filter(|&(x, y)| x < self.grid.size.0 && y < self.grid.size.1)
-                .filter(|&(x, y)| self.grid.cells[pos.1][pos.0] <= self.grid.cells[y][x] + 1)
-                .map(|p| (p, steps + 1)),
-            );
-            return Some((pos, steps));
-        }
-        return None;
+        };
+        let is_goal = |pos: &Option<_>| pos.as_ref().is_some_and(&mut is_goal);
+        dijkstra(None, neighbors, is_goal).unwrap().0
     }
 }
 
 pub(crate) fn solve(input: &str) -> usize {
     let grid = Grid::new(input);
-    BFS::new(&grid)
-        .find(|&(pos, _)| pos == grid.start)
-        .unwrap()
-        .1
+    grid.shortest_path(|&pos| pos == grid.start)
 }
 
 pub(crate) fn solve_2(input: &str) -> usize {
     let grid = Grid::new(input);
-    BFS::new(&grid)
-        .filter(|&((x, y), _)| grid.cells[y][x] == 0)
-        .min_by_key(|&(_, steps)| steps)
-        .unwrap()
-        .1
+    grid.shortest_path(|&pos| grid.cells[pos] == 0)
 }
 
 #[cfg(test)]
@@ -119,9 +92,30 @@ mod tests {
     #[test]
     fn test_parse() {
         let grid = Grid::new(EXAMPLE);
-        assert_eq!(grid.size, (8, 5));
+        assert_eq!(grid.cells.size(), (8, 5));
         assert_eq!(grid.start, (0, 0));
-        assert_eq!(grid.end, (5, 2));
+        assert_eq!(grid.ends, vec![(5, 2)]);
+    }
+
+    #[test]
+    fn test_from_heights() {
+        // The 9s form a wall, so the path has to go around them
+        let heights = vec![vec![0, 1, 2, 3], vec![9, 9, 9, 4], vec![8, 7, 6, 5]];
+        let grid = Grid::from_heights(heights, (0, 0), vec![(0, 2)]);
+        assert_eq!(grid.shortest_path(|&pos| pos == grid.start), 8);
+        assert_eq!(grid.shortest_path(|&pos| [(0, 0), (2, 0)].contains(&pos)), 6);
+        assert_eq!(grid.shortest_path(|&pos| grid.cells[pos] <= 4), 4);
+    }
+
+    #[test]
+    fn test_multiple_ends() {
+        let left = "Eyxwvutsrqponmlkjihgfedcb";
+        let right = "bcdefghijklmnopqrstuvwxyzzzE";
+        let input = format!("{left}S{right}");
+        let grid = Grid::new(&input);
+        assert_eq!(grid.ends, vec![(0, 0), (53, 0)]);
+        assert_eq!(solve(&input), 25);
+        assert_eq!(solve(&input.replacen('E', "z", 1)), 28);
     }
 
     #[test]
@@ -1,6 +1,36 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, fmt::Display, slice};
 
-use itertools::{EitherOrBoth, Itertools};
+use itertools::Itertools;
+
+use crate::utils::clean_lines;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ParseError {
+    Unexpected { character: char, position: usize },
+    UnexpectedEnd,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unexpected {
+                character,
+                position,
+            } => write!(f, "unexpected {character:?} at position {position}"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+// What the JSON parser will accept next, ignoring whitespace
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Open,
+    Value,
+    ValueOrClose,
+    SeparatorOrClose,
+    End,
+}
 
 #[derive(Debug, Clone)]
 enum Value {
@@ -43,35 +73,80 @@ impl Value {
         panic!("Unexpected end of input");
     }
 
-    fn compare(&self, other: &Self) -> Ordering {
-        let cmp_single = |l: &Value, b: &[Value]| {
-            if let Some(r) = b.first() {
-                let ordering = l.compare(r);
-                if ordering != Ordering::Equal {
-                    return ordering;
-                }
+    // Stricter alternative to `new`: allows any JSON whitespace between tokens but
+    // rejects stray characters, missing or trailing commas, and unbalanced brackets.
+    fn parse_json(input: &str) -> Result<Self, ParseError> {
+        let mut stack: Vec<Vec<Value>> = Vec::new();
+        let mut int = None;
+        let mut expect = Expect::Open;
+        let mut result = None;
+
+        for (position, c) in input.chars().enumerate() {
+            let unexpected = ParseError::Unexpected {
+                character: c,
+                position,
+            };
+            if let Some(d) = c.to_digit(10) {
+                int = Some(match int.take() {
+                    Some(int) => int * 10 + d as usize,
+                    None if matches!(expect, Expect::Value | Expect::ValueOrClose) => d as usize,
+                    None => return Err(unexpected),
+                });
+                continue;
             }
-            1.cmp(&b.len())
-        };
-        let cmp_slice = |a: &[Value], b: &[Value]| {
-            for item in a.iter().zip_longest(b.iter()) {
-                match item {
-                    EitherOrBoth::Left(_) => return Ordering::Greater,
-                    EitherOrBoth::Right(_) => return Ordering::Less,
-                    EitherOrBoth::Both(l, r) => match l.compare(r) {
-                        Ordering::Equal => continue,
-                        ordering => return ordering,
-                    },
+            if let Some(int) = int.take() {
+                stack.last_mut().unwrap().push(Value::Integer(int));
+                expect = Expect::SeparatorOrClose;
+            }
+            match c {
+                c if c.is_whitespace() => {}
+                '[' if matches!(expect, Expect::Open | Expect::Value | Expect::ValueOrClose) => {
+                    stack.push(Vec::new());
+                    expect = Expect::ValueOrClose;
+                }
+                ']' if matches!(expect, Expect::ValueOrClose | Expect::SeparatorOrClose) => {
+                    let list = Value::List(stack.pop().unwrap());
+                    match stack.last_mut() {
+                        Some(parent) => {
+                            parent.push(list);
+                            expect = Expect::SeparatorOrClose;
+                        }
+                        None => {
+                            result = Some(list);
+                            expect = Expect::End;
+                        }
+                    }
                 }
+                ',' if expect == Expect::SeparatorOrClose => expect = Expect::Value,
+                _ => return Err(unexpected),
             }
-            Ordering::Equal
-        };
-        match (self, other) {
-            (Self::List(l), Self::List(r)) => cmp_slice(l, r),
-            (l, Self::List(r)) => cmp_single(l, r),
-            (Self::List(l), r) => cmp_single(r, l).reverse(),
-            (Self::Integer(l), Self::Integer(r)) => l.cmp(r),
         }
+        result.ok_or(ParseError::UnexpectedEnd)
+    }
+
+    // Compares lists element by element using an explicit stack, so deeply nested packets
+    // can't overflow. An integer compared with a list is promoted to a one-element list.
+    fn compare(&self, other: &Self) -> Ordering {
+        let mut stack = vec![(slice::from_ref(self), slice::from_ref(other))];
+        while let Some((a, b)) = stack.pop() {
+            let ((l, a), (r, b)) = match (a.split_first(), b.split_first()) {
+                (None, None) => continue,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a), Some(b)) => (a, b),
+            };
+            stack.push((a, b));
+            match (l, r) {
+                (Self::Integer(l), Self::Integer(r)) => match l.cmp(r) {
+                    Ordering::Equal => continue,
+                    ordering => return ordering,
+                },
+                (Self::List(l), Self::List(r)) => stack.push((l, r)),
+                (l, Self::List(r)) => stack.push((slice::from_ref(l), r)),
+                (Self::List(l), r) => stack.push((l, slice::from_ref(r))),
+            }
+        }
+        Ordering::Equal
     }
 }
 
@@ -96,19 +171,33 @@ impl Ord for Value {
 }
 
 fn parse(input: &str) -> impl Iterator<Item = Value> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .map(|l| Value::new(l))
 }
 
-pub(crate) fn solve(input: &str) -> usize {
+const EXPECTED: &str = "a JSON list of integers and lists";
+
+// Checks every packet with the strict parser, since `new` panics on malformed ones
+pub(crate) fn parse_count(input: &str) -> Result<usize, crate::utils::ParseError> {
+    clean_lines(input).try_fold(0, |count, l| match Value::parse_json(l) {
+        Ok(_) => Ok(count + 1),
+        Err(_) => Err(crate::utils::ParseError::new(l, EXPECTED)),
+    })
+}
+
+// How each pair compares, so callers can spot `Equal` pairs, which the puzzle
+// never produces but fuzzed input might
+fn ordered_pairs(input: &str) -> impl Iterator<Item = Ordering> + '_ {
     parse(input)
         .tuples()
-        .map(|(l, r)| (l, r))
+        .map(|(l, r): (Value, Value)| l.compare(&r))
+}
+
+// Only strictly ordered pairs count, so an `Equal` pair is treated as out of order
+pub(crate) fn solve(input: &str) -> usize {
+    ordered_pairs(input)
         .enumerate()
-        .filter(|(_, (l, r))| l < r)
+        .filter(|&(_, ordering)| ordering == Ordering::Less)
         .map(|(i, _)| i + 1)
         .sum()
 }
@@ -183,11 +272,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_json() {
+        // `Value`'s equality promotes integers to lists, so compare the exact structure
+        for line in clean_lines(EXAMPLE) {
+            assert_eq!(
+                format!("{:?}", Value::parse_json(line).unwrap()),
+                format!("{:?}", Value::new(line))
+            );
+        }
+        assert_eq!(
+            format!(
+                "{:?}",
+                Value::parse_json(" [ 10,\n\t[ ] , [3 ] ]\n").unwrap()
+            ),
+            format!("{:?}", Value::new("[10,[],[3]]"))
+        );
+    }
+
+    #[test]
+    fn test_parse_json_invalid() {
+        let unexpected = |character, position| ParseError::Unexpected {
+            character,
+            position,
+        };
+        assert_eq!(Value::parse_json("[1,,2]"), Err(unexpected(',', 3)));
+        assert_eq!(Value::parse_json("[1,2,]"), Err(unexpected(']', 5)));
+        assert_eq!(Value::parse_json("[1 2]"), Err(unexpected('2', 3)));
+        assert_eq!(Value::parse_json("[[1]]]"), Err(unexpected(']', 5)));
+        assert_eq!(Value::parse_json("[a]"), Err(unexpected('a', 1)));
+        assert_eq!(Value::parse_json("1"), Err(unexpected('1', 0)));
+        assert_eq!(Value::parse_json("[[1]"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(Value::parse_json(""), Err(ParseError::UnexpectedEnd));
+        assert_eq!(
+            Value::parse_json("[1,x]").unwrap_err().to_string(),
+            "unexpected 'x' at position 3"
+        );
+    }
+
     #[test]
     fn test_parse() {
         assert_eq!(parse(EXAMPLE).collect_vec().len(), 16);
     }
 
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count(EXAMPLE), Ok(16));
+        assert_eq!(
+            parse_count("[1,2]\n[1,,2]"),
+            Err(crate::utils::ParseError::new("[1,,2]", EXPECTED))
+        );
+    }
+
     #[test]
     fn test_ord() {
         let l = Value::List(vec![
@@ -211,6 +347,37 @@ mod tests {
         assert!(Value::new("[]") < Value::new("[[]]"));
     }
 
+    #[test]
+    fn test_deep_nesting() {
+        const DEPTH: usize = 10_000;
+        let nested = |depth, inner: &str| {
+            Value::new(&format!(
+                "{}{inner}{}",
+                "[".repeat(depth),
+                "]".repeat(depth)
+            ))
+        };
+        assert!(nested(DEPTH, "1") < nested(DEPTH, "2"));
+        assert_eq!(nested(DEPTH, "1"), nested(DEPTH, "1"));
+        assert_eq!(nested(DEPTH, "1"), nested(DEPTH - 1, "1"));
+        assert!(nested(DEPTH, "") > nested(DEPTH - 1, ""));
+    }
+
+    #[test]
+    fn test_ordered_pairs() {
+        use Ordering::*;
+        let orderings = ordered_pairs(EXAMPLE).collect_vec();
+        assert_eq!(
+            orderings,
+            vec![Less, Less, Greater, Less, Greater, Less, Greater, Greater]
+        );
+
+        // An integer is promoted to a list, so these pairs are equal
+        let input = "[1,[2]]\n[[1],2]\n\n[]\n[1]\n\n[3]\n[[[3]]]";
+        assert_eq!(ordered_pairs(input).collect_vec(), vec![Equal, Less, Equal]);
+        assert_eq!(solve(input), 2);
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(solve(EXAMPLE), 13);
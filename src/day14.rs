@@ -1,7 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
 use itertools::Itertools;
 
+use crate::utils::{clean_lines, Visualize};
+
+type Point = (isize, isize);
+
 #[derive(Debug, PartialEq, Eq)]
 enum Line {
     Vertical(isize, (isize, isize)),
@@ -66,19 +70,33 @@ impl Cells {
         }
     }
 
-    fn add_sand(&mut self, mut point: (isize, isize)) -> bool {
+    // Where the grain dropped from `point` comes to rest, or `None` if it falls out of
+    // bounds or `point` is already blocked
+    fn drop_one(&mut self, point: Point) -> Option<Point> {
+        self.fall(point, |_| ())
+    }
+
+    // Like `drop_one`, also returning every in-bounds cell the grain passed through
+    fn drop_one_traced(&mut self, point: Point) -> (Option<Point>, Vec<Point>) {
+        let mut path = Vec::new();
+        let rest = self.fall(point, |p| path.push(p));
+        (rest, path)
+    }
+
+    fn fall(&mut self, mut point: Point, mut visit: impl FnMut(Point)) -> Option<Point> {
         if self.occupied_cells.contains_key(&point) {
-            return false;
+            return None;
         }
         loop {
             match self.min_bound {
                 Some(min) if self.new_min_bound(point) == min => (),
-                _ => return false,
+                _ => return None,
             }
             match self.max_bound {
                 Some(max) if self.new_max_bound(point) == max => (),
-                _ => return false,
+                _ => return None,
             }
+            visit(point);
             let (x, y) = point;
             let next_point = [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)]
                 .into_iter()
@@ -88,22 +106,45 @@ impl Cells {
             } else {
                 // Sand comes to rest at `point`
                 self.occupied_cells.insert(point, CellType::Sand);
-                return true;
+                return Some(point);
             }
         }
     }
 
-    fn _to_string(&self) -> String {
+    // The lowest resting grain of sand, leftmost on ties
+    fn deepest_rest(&self) -> Option<(isize, isize)> {
+        self.occupied_cells
+            .iter()
+            .filter(|&(_, cell)| matches!(cell, CellType::Sand))
+            .map(|(&point, _)| point)
+            .max_by_key(|&(x, y)| (y, -x))
+    }
+
+    // (sand, wall)
+    fn counts(&self) -> (usize, usize) {
+        self.occupied_cells
+            .values()
+            .fold((0, 0), |(sand, wall), cell| match cell {
+                CellType::Sand => (sand + 1, wall),
+                CellType::Wall => (sand, wall + 1),
+            })
+    }
+
+    // Like `visualize`, with the empty cells on `path`, such as a grain's fall
+    // from the source, drawn as `~`
+    fn render_with_path(&self, path: &[(isize, isize)]) -> String {
         let mut result = String::new();
         let (min, max) = match (self.min_bound, self.max_bound) {
             (Some(min), Some(max)) => (min, max),
             _ => return result,
         };
+        let path: HashSet<_> = path.iter().collect();
         for y in min.1..=max.1 {
             for x in min.0..=max.0 {
                 let c = match self.occupied_cells.get(&(x, y)) {
                     Some(CellType::Wall) => '#',
                     Some(CellType::Sand) => 'o',
+                    None if path.contains(&(x, y)) => '~',
                     None => '.',
                 };
                 result.push(c);
@@ -114,11 +155,14 @@ impl Cells {
     }
 }
 
+impl Visualize for Cells {
+    fn visualize(&self) -> String {
+        self.render_with_path(&[])
+    }
+}
+
 fn parse(input: &str) -> impl Iterator<Item = Line> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .flat_map(|l| {
             l.split(" -> ")
                 .map(|p| {
@@ -132,13 +176,39 @@ fn parse(input: &str) -> impl Iterator<Item = Line> + '_ {
         })
 }
 
+// The cavern once it's full, with the path of the first grain to fall out
+pub(crate) fn visualize(input: &str) -> String {
+    let mut cells = parse(input).fold(Cells::new(), |mut cell, line| {
+        cell.add_line(line);
+        cell
+    });
+    loop {
+        if let (None, path) = cells.drop_one_traced((500, 0)) {
+            return cells.render_with_path(&path);
+        }
+    }
+}
+
+pub(crate) fn report(input: &str) -> String {
+    let mut cells = parse(input).fold(Cells::new(), |mut cell, line| {
+        cell.add_line(line);
+        cell
+    });
+    while cells.drop_one((500, 0)).is_some() {}
+    let (sand, wall) = cells.counts();
+    match cells.deepest_rest() {
+        Some((x, y)) => format!("{sand} sand, {wall} wall, deepest grain at {x},{y}"),
+        None => format!("{sand} sand, {wall} wall"),
+    }
+}
+
 pub(crate) fn solve(input: &str) -> usize {
     let mut cells = parse(input).fold(Cells::new(), |mut cell, line| {
         cell.add_line(line);
         cell
     });
     for i in 0.. {
-        if !cells.add_sand((500, 0)) {
+        if cells.drop_one((500, 0)).is_none() {
             return i;
         }
     }
@@ -153,7 +223,7 @@ pub(crate) fn solve_2(input: &str) -> usize {
     let depth = cells.max_bound.unwrap().1 + 2;
     cells.add_line(Line::Horizontal((500 - depth, 500 + depth), depth));
     for i in 0.. {
-        if !cells.add_sand((500, 0)) {
+        if cells.drop_one((500, 0)).is_none() {
             return i;
         }
     }
@@ -198,6 +268,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_visualize() {
+        assert_eq!(
+            visualize(EXAMPLE),
+            "\
+                ......~...
+                ......~...
+                .....~o...
+                ....~ooo..
+                ...~#ooo##
+                ..~o#ooo#.
+                .~###ooo#.
+                .~..oooo#.
+                ~o.ooooo#.
+                #########.
+            "
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| format!("{l}\n"))
+            .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_render_with_path() {
+        let mut cells = parse(EXAMPLE).fold(Cells::new(), |mut cell, line| {
+            cell.add_line(line);
+            cell
+        });
+        cells.drop_one((500, 0));
+        // The second grain falls straight down, then slides left off the first
+        let (rest, path) = cells.drop_one_traced((500, 0));
+        assert_eq!(rest, Some((499, 8)));
+        assert_eq!(path, (0..=7).map(|y| (500, y)).chain([(499, 8)]).collect_vec());
+        let rendered = cells.render_with_path(&path);
+        let rows = rendered.lines().collect_vec();
+        assert_eq!(rows[0], "......~...");
+        assert_eq!(rows[7], "......~.#.");
+        assert_eq!(rows[8], ".....oo.#.");
+        assert_eq!(rows[9], "#########.");
+        assert_eq!(rendered.matches('~').count(), 8);
+        assert_eq!(cells.render_with_path(&[]), cells.visualize());
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut cells = parse(EXAMPLE).fold(Cells::new(), |mut cell, line| {
+            cell.add_line(line);
+            cell
+        });
+        assert_eq!(cells.counts(), (0, 20));
+        while cells.drop_one((500, 0)).is_some() {}
+        assert_eq!(cells.counts(), (solve(EXAMPLE), 20));
+        assert_eq!(report(EXAMPLE), "24 sand, 20 wall, deepest grain at 495,8");
+        assert_eq!(report("500,0 -> 501,0"), "0 sand, 2 wall");
+    }
+
+    #[test]
+    fn test_drop_one() {
+        let mut cells = parse(EXAMPLE).fold(Cells::new(), |mut cell, line| {
+            cell.add_line(line);
+            cell
+        });
+        let resting = (0..5).map(|_| cells.drop_one((500, 0))).collect_vec();
+        assert_eq!(
+            resting,
+            vec![
+                Some((500, 8)),
+                Some((499, 8)),
+                Some((501, 8)),
+                Some((500, 7)),
+                Some((498, 8)),
+            ]
+        );
+        while cells.drop_one((500, 0)).is_some() {}
+        assert_eq!(cells.drop_one((500, 0)), None);
+    }
+
+    #[test]
+    fn test_deepest_rest() {
+        let mut cells = parse(EXAMPLE).fold(Cells::new(), |mut cell, line| {
+            cell.add_line(line);
+            cell
+        });
+        assert_eq!(cells.deepest_rest(), None);
+        cells.drop_one((500, 0));
+        assert_eq!(cells.deepest_rest(), Some((500, 8)));
+        while cells.drop_one((500, 0)).is_some() {}
+        // The grain that slid off the left of the bottom ledge
+        assert_eq!(cells.deepest_rest(), Some((495, 8)));
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(solve(EXAMPLE), 24);
@@ -1,35 +1,10 @@
-use regex::Regex;
+use std::collections::HashSet;
 
-struct EmptiesIter<'a> {
-    ranges: &'a [(isize, isize)],
-    current: isize,
-    end: isize,
-}
+use regex::Regex;
 
-impl<'a> Iterator for EmptiesIter<'a> {
-    type Item = isize;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        while self.current < self.end {
-            if let Some(&(start, end)) = self.ranges.first() {
-                if self.current < start {
-                    // Do nothing
-                } else if self.current < end {
-                    self.current = end;
-                    continue;
-                } else {
-                    self.ranges = &self.ranges[1..];
-                    continue;
-                }
-            }
-            let result = self.current;
-            self.current += 1;
-            return Some(result);
-        }
-        None
-    }
-}
+use crate::utils::{clean_lines, ParseError, Vec2};
 
+// Disjoint, sorted half-open `[start, end)` intervals
 #[derive(Clone)]
 struct Ranges {
     ranges: Vec<(isize, isize)>,
@@ -40,6 +15,7 @@ impl Ranges {
         Self { ranges: Vec::new() }
     }
 
+    // Touching intervals such as [0, 2) and [2, 4) merge into [0, 4)
     fn add(&mut self, mut range: (isize, isize)) {
         let mut i = 0;
         while i < self.ranges.len() {
@@ -60,12 +36,31 @@ impl Ranges {
         self.ranges.insert(i, range);
     }
 
-    fn empties(&self, range: (isize, isize)) -> impl Iterator<Item = isize> + '_ {
-        EmptiesIter {
-            ranges: &self.ranges,
-            current: range.0,
-            end: range.1,
+    fn empties(&self, range: (isize, isize)) -> impl Iterator<Item = isize> {
+        self.gaps(range).into_iter().flat_map(|(start, end)| start..end)
+    }
+
+    fn contains(&self, x: isize) -> bool {
+        self.ranges.iter().any(|&(start, end)| start <= x && x < end)
+    }
+
+    // Uncovered intervals within `window`, in the same form as the ranges
+    fn gaps(&self, window: (isize, isize)) -> Vec<(isize, isize)> {
+        let mut gaps = Vec::new();
+        let mut current = window.0;
+        for &(start, end) in &self.ranges {
+            if start >= window.1 {
+                break;
+            }
+            if start > current {
+                gaps.push((current, start));
+            }
+            current = current.max(end);
+        }
+        if current < window.1 {
+            gaps.push((current, window.1));
         }
+        gaps
     }
 
     fn count(&self) -> usize {
@@ -76,72 +71,95 @@ impl Ranges {
 #[derive(Debug, PartialEq, Eq)]
 struct Area {
     center: (isize, isize),
+    beacon: (isize, isize),
     radius: isize,
 }
 
-fn parse(input: &str) -> impl Iterator<Item = Area> + '_ {
+impl Area {
+    // Cells of row `y` within the radius, as a half-open interval
+    fn row_range(&self, y: isize) -> Option<(isize, isize)> {
+        let x_dist = self.radius - (self.center.1 - y).abs();
+        (x_dist >= 0).then_some((self.center.0 - x_dist, self.center.0 + x_dist + 1))
+    }
+}
+
+const EXPECTED: &str = "`Sensor at x=<x>, y=<y>: closest beacon is at x=<x>, y=<y>`";
+
+fn parse(input: &str) -> impl Iterator<Item = Result<Area, ParseError>> + '_ {
     let re = Regex::new(r"^.*=(-?\d+).*=(-?\d+).*=(-?\d+).*=(-?\d+)$").unwrap();
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .map(move |l| {
-            let cap = re.captures(l).unwrap();
-            let sensor = (
-                cap[1].parse::<isize>().unwrap(),
-                cap[2].parse::<isize>().unwrap(),
-            );
-            let beacon = (
-                cap[3].parse::<isize>().unwrap(),
-                cap[4].parse::<isize>().unwrap(),
-            );
-            Area {
+            let error = || ParseError::new(l, EXPECTED);
+            let cap = re.captures(l).ok_or_else(error)?;
+            let num = |i: usize| cap[i].parse::<isize>().map_err(|_| error());
+            let sensor = (num(1)?, num(2)?);
+            let beacon = (num(3)?, num(4)?);
+            let offset = Vec2::new(sensor.0 - beacon.0, sensor.1 - beacon.1);
+            Ok(Area {
                 center: sensor,
-                radius: (sensor.0 - beacon.0).abs() + (sensor.1 - beacon.1).abs(),
-            }
+                beacon,
+                radius: offset.manhattan(),
+            })
         })
 }
 
-fn compute<const N: isize>(input: &str) -> usize {
-    parse(input)
-        .filter_map(|area| match (area.center.1 - N).abs() {
-            y_dist if y_dist < area.radius => {
-                let x_dist = area.radius - y_dist;
-                Some((area.center.0 - x_dist, area.center.0 + x_dist))
-            }
-            _ => None,
-        })
+// Cells in row `y` that can't hold an unknown beacon
+fn row_coverage(areas: &[Area], y: isize) -> usize {
+    let covered = areas
+        .iter()
+        .filter_map(|area| area.row_range(y))
         .fold(Ranges::new(), |mut ranges, r| {
             ranges.add(r);
             ranges
-        })
-        .count()
+        });
+    // A known beacon on the row can't be an unknown one, but only counts if covered
+    let beacons = areas
+        .iter()
+        .map(|area| area.beacon)
+        .filter(|beacon| beacon.1 == y && covered.contains(beacon.0))
+        .collect::<HashSet<_>>();
+    covered.count() - beacons.len()
+}
+
+// Coverage of each row in the half-open `y_range`, parsing the input once
+fn coverage_per_row(input: &str, y_range: (isize, isize)) -> Result<Vec<usize>, ParseError> {
+    let areas = parse(input).collect::<Result<Vec<_>, _>>()?;
+    Ok((y_range.0..y_range.1)
+        .map(|y| row_coverage(&areas, y))
+        .collect())
+}
+
+fn compute<const N: isize>(input: &str) -> Result<usize, ParseError> {
+    Ok(coverage_per_row(input, (N, N + 1))?[0])
 }
 
-fn compute_2<const MAX: isize>(input: &str) -> isize {
+fn compute_2<const MAX: isize>(input: &str) -> Result<isize, ParseError> {
     let mut rows = vec![Ranges::new(); MAX as usize];
     for area in parse(input) {
+        let area = area?;
         let y_min = (area.center.1 - area.radius).max(0);
         let y_max = (area.center.1 + area.radius).min(MAX);
         for y in y_min..y_max {
-            let y_dist = (area.center.1 - y).abs();
-            let x_dist = area.radius - y_dist;
-            rows[y as usize].add((area.center.0 - x_dist, area.center.0 + x_dist + 1));
+            rows[y as usize].add(area.row_range(y).unwrap());
         }
     }
     for (y, ranges) in rows.iter().enumerate() {
         if let Some(x) = ranges.empties((0, MAX)).next() {
-            return x * 4000000 + y as isize;
+            return Ok(x * 4000000 + y as isize);
         }
     }
     panic!()
 }
 
-pub(crate) fn solve(input: &str) -> usize {
+pub(crate) fn parse_count(input: &str) -> Result<usize, ParseError> {
+    parse(input).collect::<Result<Vec<_>, _>>().map(|areas| areas.len())
+}
+
+pub(crate) fn solve(input: &str) -> Result<usize, ParseError> {
     compute::<2000000>(input)
 }
 
-pub(crate) fn solve_2(input: &str) -> isize {
+pub(crate) fn solve_2(input: &str) -> Result<isize, ParseError> {
     compute_2::<4000000>(input)
 }
 
@@ -170,16 +188,31 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let area = parse(EXAMPLE).next().unwrap();
+        let area = parse(EXAMPLE).next().unwrap().unwrap();
         assert_eq!(
             area,
             Area {
                 center: (2, 18),
+                beacon: (-2, 15),
                 radius: 7
             }
         );
     }
 
+    #[test]
+    fn test_parse_malformed() {
+        let input = "Sensor at x=2, y=18: closest beacon is at x=-2\n";
+        let error = ParseError::new("Sensor at x=2, y=18: closest beacon is at x=-2", EXPECTED);
+        assert_eq!(parse(input).next().unwrap().unwrap_err(), error);
+        assert_eq!(parse_count(input), Err(error));
+        assert_eq!(
+            solve("Sensor at x=99999999999999999999, y=0: closest beacon is at x=0, y=0")
+                .unwrap_err()
+                .line,
+            "Sensor at x=99999999999999999999, y=0: closest beacon is at x=0, y=0"
+        );
+    }
+
     #[test]
     fn test_ranges() {
         let mut ranges = Ranges::new();
@@ -207,13 +240,65 @@ mod tests {
         assert_eq!(ranges.empties((4, 13)).collect_vec(), vec![4, 10, 12]);
     }
 
+    #[test]
+    fn test_contains_and_gaps() {
+        let mut ranges = Ranges::new();
+        ranges.add((0, 1));
+        ranges.add((8, 26));
+        ranges.add((27, 29));
+        assert!(ranges.contains(0));
+        assert!(!ranges.contains(1));
+        assert!(ranges.contains(25));
+        assert!(!ranges.contains(26));
+        assert!(!ranges.contains(-1));
+        assert_eq!(ranges.gaps((-2, 30)), vec![(-2, 0), (1, 8), (26, 27), (29, 30)]);
+        assert_eq!(ranges.gaps((10, 20)), vec![]);
+        assert_eq!(ranges.gaps((5, 28)), vec![(5, 8), (26, 27)]);
+        assert_eq!(Ranges::new().gaps((3, 5)), vec![(3, 5)]);
+    }
+
+    #[test]
+    fn test_half_open_endpoints() {
+        let mut ranges = Ranges::new();
+        ranges.add((0, 2));
+        ranges.add((2, 4));
+        assert_eq!(ranges.ranges, vec![(0, 4)]);
+        assert_eq!(ranges.count(), 4);
+        ranges.add((5, 6));
+        assert_eq!(ranges.ranges, vec![(0, 4), (5, 6)]);
+        assert_eq!(ranges.count(), 5);
+        assert_eq!(ranges.empties((-1, 7)).collect_vec(), vec![-1, 4, 6]);
+
+        let area = Area {
+            center: (0, 0),
+            beacon: (2, 0),
+            radius: 2,
+        };
+        assert_eq!(area.row_range(0), Some((-2, 3)));
+        assert_eq!(area.row_range(-2), Some((0, 1)));
+        assert_eq!(area.row_range(3), None);
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(compute::<10>(EXAMPLE), 26);
+        assert_eq!(compute::<10>(EXAMPLE), Ok(26));
+    }
+
+    #[test]
+    fn test_coverage_per_row() {
+        let rows = coverage_per_row(EXAMPLE, (9, 12)).unwrap();
+        assert_eq!(rows[1], 26);
+        let expected = vec![
+            compute::<9>(EXAMPLE).unwrap(),
+            compute::<10>(EXAMPLE).unwrap(),
+            compute::<11>(EXAMPLE).unwrap(),
+        ];
+        assert_eq!(rows, expected);
+        assert_eq!(coverage_per_row(EXAMPLE, (3, 3)), Ok(vec![]));
     }
 
     #[test]
     fn test_solve_2() {
-        assert_eq!(compute_2::<20>(EXAMPLE), 56000011);
+        assert_eq!(compute_2::<20>(EXAMPLE), Ok(56000011));
     }
 }
@@ -1,7 +1,13 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    iter::zip,
+};
 
+use itertools::Itertools;
 use regex::Regex;
 
+use crate::utils::{clean_lines, DpStats, MemoStats, ParseError};
+
 struct Edge {
     cost: u8,
     to_node: u8,
@@ -14,21 +20,23 @@ struct Node {
 
 struct Graph {
     nodes: Vec<Node>,
+    // Valve names, indexed by node id
+    names: Vec<String>,
     initial_node: u8,
 }
 
 impl Graph {
     fn new<'a>(valves: impl Iterator<Item = Valve<'a>>) -> Self {
+        // Built in compact form, each rate with its `(to_node, cost)` edges
         let mut nodes = Vec::new();
+        let mut names = Vec::new();
         let mut name_id_map = HashMap::new();
         let mut named_valves = HashMap::new();
         for valve in valves {
             if valve.rate > 0 || valve.name == "AA" {
                 name_id_map.insert(valve.name, nodes.len());
-                nodes.push(Node {
-                    rate: valve.rate,
-                    edges: Vec::new(),
-                })
+                nodes.push((valve.rate, Vec::new()));
+                names.push(valve.name.to_string());
             }
             named_valves.insert(valve.name, valve);
         }
@@ -46,16 +54,71 @@ impl Graph {
                     continue;
                 };
                 if id != root_id {
-                    nodes[root_id].edges.push(Edge {
-                        cost: distance + 1,
-                        to_node: id as u8,
-                    })
+                    nodes[root_id].1.push((id as u8, distance + 1));
                 }
             }
         }
+        Self {
+            names,
+            ..Self::from_compact(nodes, name_id_map["AA"] as u8)
+        }
+    }
+
+    #[cfg(test)]
+    fn from_valves(valves: Vec<(String, u8, Vec<String>)>) -> Self {
+        Self::new(valves.iter().map(|(name, rate, connections)| Valve {
+            name,
+            rate: *rate,
+            connections: connections.iter().map(String::as_str).collect(),
+        }))
+    }
+
+    // Includes the starting valve, which is kept even when its rate is zero
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn total_flow(&self) -> usize {
+        self.nodes.iter().map(|node| node.rate as usize).sum()
+    }
+
+    // The valves whose node bits are set, such as `SolveState::allowed`, by id
+    fn names_in_mask(&self, mask: u32) -> Vec<&str> {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(|&(id, _)| mask & (1 << id) != 0)
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+
+    // Each node's rate and `(to_node, cost)` edges, by id, for storing the collapsed
+    // graph without redoing the BFS. Valve names aren't included.
+    fn to_compact(&self) -> Vec<(u8, Vec<(u8, u8)>)> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let edges = node.edges.iter().map(|e| (e.to_node, e.cost)).collect();
+                (node.rate, edges)
+            })
+            .collect()
+    }
+
+    fn from_compact(compact: Vec<(u8, Vec<(u8, u8)>)>, initial_node: u8) -> Self {
+        let nodes = compact
+            .into_iter()
+            .map(|(rate, edges)| Node {
+                rate,
+                edges: edges
+                    .into_iter()
+                    .map(|(to_node, cost)| Edge { cost, to_node })
+                    .collect(),
+            })
+            .collect();
         Self {
             nodes,
-            initial_node: name_id_map["AA"] as u8,
+            names: Vec::new(),
+            initial_node,
         }
     }
 }
@@ -115,11 +178,18 @@ struct SolveState {
 struct Solver<'a> {
     graph: &'a Graph,
     memo: Option<Box<dyn Memo<usize>>>,
+    stats: MemoStats,
+    max_states: usize,
 }
 
 impl<'a> Solver<'a> {
     fn new(graph: &'a Graph) -> Self {
-        Self { memo: None, graph }
+        Self {
+            memo: None,
+            graph,
+            stats: MemoStats::default(),
+            max_states: usize::MAX,
+        }
     }
 
     fn solve<T: BuildableMemo<usize> + Memo<usize> + 'static>(
@@ -127,7 +197,79 @@ impl<'a> Solver<'a> {
         num_actors: usize,
         budget: i8,
     ) -> usize {
+        self.solve_with_budget::<T>(num_actors, budget, usize::MAX).unwrap()
+    }
+
+    // Best pressure a single actor can release in `budget` minutes. Memoized scores
+    // include what was released on the way to each state, which depends on the
+    // starting budget, so each call starts from a fresh memo.
+    fn best_for_budget(&mut self, budget: i8) -> usize {
+        self.solve::<HashMap<SolveState, usize>>(1, budget)
+    }
+
+    // A feasible single-actor score from always heading for the highest-rate valve
+    // that can still be opened in time. Seeding the search with it prunes nothing,
+    // since partitions are only compared after they're fully searched.
+    #[cfg(test)]
+    fn greedy_lower_bound(&self, mut budget: i8) -> usize {
+        let mut node = self.graph.initial_node;
+        let mut opened = 1u32 << node;
+        let mut score = 0;
+        while let Some(edge) = self.graph.nodes[node as usize]
+            .edges
+            .iter()
+            .filter(|edge| opened & (1 << edge.to_node) == 0)
+            .filter(|edge| budget - edge.cost as i8 > 1)
+            .max_by_key(|edge| self.graph.nodes[edge.to_node as usize].rate)
+        {
+            node = edge.to_node;
+            opened |= 1 << node;
+            budget -= edge.cost as i8;
+            score += budget as usize * self.graph.nodes[node as usize].rate as usize;
+        }
+        score
+    }
+
+    // Tries every order of opening the valves with one actor, without memoization.
+    // Only feasible for graphs as small as the example.
+    #[cfg(test)]
+    fn brute_force(&self, budget: i8) -> usize {
+        use itertools::Itertools;
+
+        let initial_node = self.graph.initial_node;
+        let cost = |from: u8, to: u8| {
+            let edges = &self.graph.nodes[from as usize].edges;
+            edges.iter().find(|edge| edge.to_node == to).unwrap().cost as i8
+        };
+        let valves = (0..self.graph.nodes.len() as u8).filter(|&node| node != initial_node);
+        valves
+            .permutations(self.graph.nodes.len() - 1)
+            .map(|order| {
+                let (mut node, mut budget, mut score) = (initial_node, budget, 0);
+                for next in order {
+                    budget -= cost(node, next);
+                    if budget <= 1 {
+                        break;
+                    }
+                    node = next;
+                    score += budget as usize * self.graph.nodes[node as usize].rate as usize;
+                }
+                score
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Like `solve`, but gives up once `recurse` has visited `max_states` states
+    fn solve_with_budget<T: BuildableMemo<usize> + Memo<usize> + 'static>(
+        &mut self,
+        num_actors: usize,
+        budget: i8,
+        max_states: usize,
+    ) -> Option<usize> {
         self.memo.replace(Box::new(T::new(self.graph, budget)));
+        self.stats = MemoStats::default();
+        self.max_states = max_states;
         let initial_node = self.graph.initial_node;
         let initial_state = SolveState {
             node: initial_node,
@@ -146,7 +288,7 @@ impl<'a> Solver<'a> {
                         allowed: initial_state.allowed | nodes,
                         ..initial_state
                     };
-                    score += self.recurse(state, 0);
+                    score += self.recurse(state, 0)?;
                 }
                 best_score = best_score.max(score);
                 continue;
@@ -157,18 +299,24 @@ impl<'a> Solver<'a> {
                 stack.push((node + 1, allowed_nodes));
             }
         }
-        best_score
+        Some(best_score)
     }
 
-    fn recurse(&mut self, mut state: SolveState, mut score: usize) -> usize {
+    // `None` once the state budget is used up; every lookup counts as a visit
+    fn recurse(&mut self, mut state: SolveState, mut score: usize) -> Option<usize> {
         if state.budget <= 1 {
-            return score;
+            return Some(score);
         }
         if state.allowed & (1 << state.node) == 0 {
-            return score;
+            return Some(score);
+        }
+        if self.stats.lookups == self.max_states {
+            return None;
         }
+        self.stats.lookups += 1;
         if let Some(result) = self.memo.as_ref().unwrap().get(&state) {
-            return result;
+            self.stats.hits += 1;
+            return Some(result);
         }
 
         state.allowed &= !(1 << state.node);
@@ -181,11 +329,18 @@ impl<'a> Solver<'a> {
                 budget: state.budget - cost as i8,
                 ..state
             };
-            best_score = self.recurse(state, score).max(best_score);
+            best_score = self.recurse(state, score)?.max(best_score);
         }
 
         self.memo.as_mut().unwrap().insert(state, best_score);
-        best_score
+        self.stats.entries += 1;
+        Some(best_score)
+    }
+}
+
+impl DpStats for Solver<'_> {
+    fn dp_stats(&self) -> MemoStats {
+        self.stats
     }
 }
 
@@ -195,42 +350,91 @@ struct Valve<'a> {
     connections: Vec<&'a str>,
 }
 
+const EXPECTED: &str = "`Valve <name> has flow rate=<rate>; tunnels lead to valves <name>, ...`";
+
 impl<'a> Valve<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
         let re = Regex::new(r"^Valve (\w+) has flow rate=(\d+); tunnels? leads? to valves? (.*)$")
             .unwrap();
-        let cap = re.captures(input).unwrap();
-        Self {
+        let error = || ParseError::new(input, EXPECTED);
+        let cap = re.captures(input).ok_or_else(error)?;
+        Ok(Self {
             name: cap.get(1).unwrap().into(),
-            rate: cap.get(2).unwrap().as_str().parse().unwrap(),
+            rate: cap[2].parse().map_err(|_| error())?,
             connections: cap.get(3).unwrap().as_str().split(", ").collect(),
-        }
+        })
     }
 }
 
-fn parse(input: &str) -> impl Iterator<Item = Valve> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+fn parse(input: &str) -> impl Iterator<Item = Result<Valve, ParseError>> + '_ {
+    clean_lines(input)
         .map(Valve::new)
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    let graph = Graph::new(parse(input));
+fn parse_graph(input: &str) -> Result<Graph, ParseError> {
+    let valves = parse(input).collect::<Result<Vec<_>, _>>()?;
+    Ok(Graph::new(valves.into_iter()))
+}
+
+pub(crate) fn parse_count(input: &str) -> Result<usize, ParseError> {
+    parse(input).collect::<Result<Vec<_>, _>>().map(|valves| valves.len())
+}
+
+// The collapsed graph, each kept valve with the minutes to reach and open the others
+pub(crate) fn visualize(input: &str) -> String {
+    let graph = match parse_graph(input) {
+        Ok(graph) => graph,
+        Err(e) => return e.to_string(),
+    };
+    zip(&graph.names, graph.to_compact())
+        .map(|(name, (rate, edges))| {
+            let edges = edges
+                .into_iter()
+                .map(|(to_node, cost)| format!("{} {cost}", graph.names[to_node as usize]))
+                .join(", ");
+            format!("{name} (rate {rate}): {edges}")
+        })
+        .join("\n")
+}
+
+pub(crate) fn report(input: &str) -> String {
+    match parse_graph(input) {
+        Ok(graph) => format!(
+            "{} valves kept ({}), with a total rate of {}",
+            graph.node_count(),
+            graph.names_in_mask(u32::MAX).join(", "),
+            graph.total_flow()
+        ),
+        Err(e) => e.to_string(),
+    }
+}
+
+pub(crate) fn solve_with_stats(input: &str) -> Result<(usize, MemoStats), ParseError> {
+    let graph = parse_graph(input)?;
     let mut solver = Solver::new(&graph);
-    solver.solve::<HashMap<SolveState, usize>>(1, 30)
+    let result = solver.best_for_budget(30);
+    Ok((result, solver.dp_stats()))
 }
 
-pub(crate) fn solve_2(input: &str) -> usize {
-    let graph = Graph::new(parse(input));
+pub(crate) fn solve_2_with_stats(input: &str) -> Result<(usize, MemoStats), ParseError> {
+    let graph = parse_graph(input)?;
     let mut solver = Solver::new(&graph);
-    solver.solve::<VecCache<usize>>(2, 26)
+    let result = solver.solve::<VecCache<usize>>(2, 26);
+    Ok((result, solver.dp_stats()))
+}
+
+pub(crate) fn solve(input: &str) -> Result<usize, ParseError> {
+    solve_with_stats(input).map(|(result, _)| result)
+}
+
+pub(crate) fn solve_2(input: &str) -> Result<usize, ParseError> {
+    solve_2_with_stats(input).map(|(result, _)| result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     const EXAMPLE: &str = "
         Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
@@ -247,20 +451,156 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let valve = parse(EXAMPLE).next().unwrap();
+        let valve = parse(EXAMPLE).next().unwrap().unwrap();
         assert_eq!(valve.name, "AA");
         assert_eq!(valve.rate, 0);
         assert_eq!(valve.connections, vec!["DD", "II", "BB"]);
     }
 
+    #[test]
+    fn test_parse_malformed() {
+        let input = "Valve AA has flow rate=0; tunnels lead to valves BB\nValve BB has rate=13";
+        let error = ParseError::new("Valve BB has rate=13", EXPECTED);
+        assert_eq!(parse_count(input), Err(error));
+        assert_eq!(
+            solve("Valve AA has flow rate=300; tunnels lead to valves BB").unwrap_err(),
+            ParseError::new("Valve AA has flow rate=300; tunnels lead to valves BB", EXPECTED)
+        );
+    }
+
+    #[test]
+    fn test_from_valves() {
+        let valve = |name: &str, rate, connections: &[&str]| {
+            let connections = connections.iter().map(|c| c.to_string()).collect();
+            (name.to_string(), rate, connections)
+        };
+        let graph = Graph::from_valves(vec![
+            valve("AA", 0, &["BB"]),
+            valve("BB", 10, &["AA", "CC"]),
+            valve("CC", 20, &["BB"]),
+        ]);
+        assert_eq!(graph.nodes.len(), 3);
+        let initial = &graph.nodes[graph.initial_node as usize];
+        let mut costs = initial
+            .edges
+            .iter()
+            .map(|e| (graph.nodes[e.to_node as usize].rate, e.cost))
+            .collect_vec();
+        costs.sort();
+        assert_eq!(costs, vec![(10, 2), (20, 3)]);
+        // Open BB on the way: 28 * 10 + 26 * 20
+        let mut solver = Solver::new(&graph);
+        assert_eq!(solver.solve::<HashMap<SolveState, usize>>(1, 30), 800);
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        let compact = graph.to_compact();
+        // AA is node 0; DD and BB are one tunnel away, HH five
+        assert_eq!(
+            compact[0],
+            (0, vec![(3, 2), (1, 2), (2, 3), (4, 3), (6, 3), (5, 6)])
+        );
+        let reloaded = Graph::from_compact(compact.clone(), graph.initial_node);
+        assert_eq!(reloaded.to_compact(), compact);
+        assert_eq!(reloaded.total_flow(), graph.total_flow());
+        let mut solver = Solver::new(&reloaded);
+        assert_eq!(solver.solve::<HashMap<SolveState, usize>>(1, 30), 1651);
+        assert_eq!(
+            visualize(EXAMPLE).lines().next(),
+            Some("AA (rate 0): DD 2, BB 2, CC 3, EE 3, JJ 3, HH 6")
+        );
+    }
+
+    #[test]
+    fn test_graph_totals() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        // Six flow-bearing valves plus AA
+        assert_eq!(graph.node_count(), 7);
+        assert_eq!(graph.total_flow(), 13 + 2 + 20 + 3 + 22 + 21);
+        assert_eq!(
+            report(EXAMPLE),
+            "7 valves kept (AA, BB, CC, DD, EE, HH, JJ), with a total rate of 81"
+        );
+    }
+
+    #[test]
+    fn test_names_in_mask() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        assert_eq!(graph.names, vec!["AA", "BB", "CC", "DD", "EE", "HH", "JJ"]);
+        assert_eq!(graph.names_in_mask(1 << graph.initial_node), vec!["AA"]);
+        assert_eq!(graph.names_in_mask(0b1001010), vec!["BB", "DD", "JJ"]);
+        assert_eq!(graph.names_in_mask(0), Vec::<&str>::new());
+        assert_eq!(graph.names_in_mask(u32::MAX).len(), graph.node_count());
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(solve(EXAMPLE), 1651);
+        assert_eq!(solve(EXAMPLE), Ok(1651));
+    }
+
+    #[test]
+    fn test_solve_with_stats() {
+        let (result, stats) = solve_with_stats(EXAMPLE).unwrap();
+        assert_eq!(result, 1651);
+        assert_eq!(stats.lookups, stats.entries + stats.hits);
+        assert!(stats.entries > 0);
+    }
+
+    #[test]
+    fn test_solve_with_budget() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        let mut solver = Solver::new(&graph);
+        let solve = |solver: &mut Solver, max_states| {
+            solver.solve_with_budget::<HashMap<SolveState, usize>>(1, 30, max_states)
+        };
+        assert_eq!(solve(&mut solver, 10), None);
+        assert_eq!(solve(&mut solver, 1_000_000), Some(1651));
+        // The exact number of states visited is just enough
+        let visited = solver.dp_stats().lookups;
+        assert_eq!(solve(&mut solver, visited), Some(1651));
+        assert_eq!(solve(&mut solver, visited - 1), None);
+    }
+
+    #[test]
+    fn test_best_for_budget() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        let mut solver = Solver::new(&graph);
+        assert_eq!(solver.best_for_budget(30), 1651);
+        assert_eq!(solver.best_for_budget(20), 852);
+        assert_eq!(solver.best_for_budget(2), 0);
+        let scores = (1..=30).map(|budget| solver.best_for_budget(budget)).collect_vec();
+        assert!(scores.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(solver.best_for_budget(30), 1651);
+    }
+
+    #[test]
+    fn test_greedy_lower_bound() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        let solver = Solver::new(&graph);
+        // HH, JJ, DD, BB, EE then CC
+        assert_eq!(solver.greedy_lower_bound(30), 1240);
+        assert!(solver.greedy_lower_bound(30) <= 1651);
+        // HH is out of reach, so JJ is the best valve left
+        assert_eq!(solver.greedy_lower_bound(6), 3 * 21);
+        assert_eq!(solver.greedy_lower_bound(2), 0);
+    }
+
+    #[test]
+    fn test_brute_force() {
+        let graph = parse_graph(EXAMPLE).unwrap();
+        let mut solver = Solver::new(&graph);
+        assert_eq!(solver.brute_force(30), 1651);
+        for budget in [2, 6, 13, 20, 30] {
+            let expected = solver.solve::<HashMap<SolveState, usize>>(1, budget);
+            assert_eq!(solver.brute_force(budget), expected);
+        }
     }
 
     #[test]
     fn test_solve_2_vec() {
-        let graph = Graph::new(parse(EXAMPLE));
+        let graph = parse_graph(EXAMPLE).unwrap();
         let mut solver = Solver::new(&graph);
         let result = solver.solve::<VecCache<usize>>(2, 26);
         assert_eq!(result, 1707)
@@ -268,7 +608,7 @@ mod tests {
 
     #[test]
     fn test_solve_2_hashmap() {
-        let graph = Graph::new(parse(EXAMPLE));
+        let graph = parse_graph(EXAMPLE).unwrap();
         let mut solver = Solver::new(&graph);
         let result = solver.solve::<HashMap<SolveState, usize>>(2, 26);
         assert_eq!(result, 1707)
@@ -1,7 +1,9 @@
-use std::{collections::BTreeMap, iter::zip};
+use std::iter::zip;
 
 use itertools::Itertools;
 
+use crate::utils::{detect_cycle, CycleInfo};
+
 const WIDTH: i8 = 7;
 const STARTING_COL: i8 = 2;
 
@@ -14,15 +16,34 @@ impl Board {
         Self { rows: Vec::new() }
     }
 
+    // Rows are bottom-up, using the same bit layout as `Shape`
+    #[cfg(test)]
+    fn from_rows(rows: Vec<u8>) -> Result<Self, String> {
+        // Columns beyond WIDTH are the low bits of each row
+        let outside = (1u8 << (8 - WIDTH)) - 1;
+        if let Some((i, row)) = rows.iter().find_position(|&&row| row & outside != 0) {
+            return Err(format!("row {i} ({row:#010b}) is wider than {WIDTH}"));
+        }
+        if rows.last() == Some(&0) {
+            return Err("top row is empty".into());
+        }
+        Ok(Self { rows })
+    }
+
     fn height(&self) -> usize {
         self.rows.len()
     }
 
+    // Up to `n` of the highest rows, still bottom-up
+    fn top_rows(&self, n: usize) -> &[u8] {
+        &self.rows[self.height().saturating_sub(n)..]
+    }
+
     fn impassable_ceiling(&self) -> Option<[u8; 4]> {
-        if self.height() < 4 {
+        let ceiling = self.top_rows(4);
+        if ceiling.len() < 4 {
             return None;
         }
-        let ceiling = &self.rows[self.height() - 4..];
         if ceiling.iter().fold(0, |or, r| or | r).count_ones() as i8 != WIDTH {
             return None;
         }
@@ -51,7 +72,7 @@ impl Board {
         for (s, b) in zip(&shape.rows, &mut self.rows[shape_bottom..]) {
             *b |= s;
         }
-        if shape_bottom + shape.rows.len() <= self.height() {
+        if shape_bottom + shape.height() <= self.height() {
             return;
         }
         for &shape_row in &shape.rows[self.height() - shape_bottom..] {
@@ -103,8 +124,7 @@ impl Shape {
             .rev()
             .map(|row| row.iter().fold(0, |accum, cell| (accum << 1) | cell))
             .collect_vec();
-        let first_col = rows.iter().map(|row| row.leading_zeros()).min().unwrap() as i8;
-        let last_col = 7 - rows.iter().map(|row| row.trailing_zeros()).min().unwrap() as i8;
+        let (first_col, last_col) = Self::cols(&rows);
         let mut result = Self {
             rows,
             first_col,
@@ -114,8 +134,23 @@ impl Shape {
         result
     }
 
+    fn cols(rows: &[u8]) -> (i8, i8) {
+        let first_col = rows.iter().map(|row| row.leading_zeros()).min().unwrap() as i8;
+        let last_col = 7 - rows.iter().map(|row| row.trailing_zeros()).min().unwrap() as i8;
+        (first_col, last_col)
+    }
+
+    fn width(&self) -> i8 {
+        self.last_col - self.first_col + 1
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
     fn shift(&mut self, amount: i8) {
-        if self.first_col + amount < 0 || self.last_col + amount >= WIDTH {
+        let first_col = self.first_col + amount;
+        if first_col < 0 || first_col + self.width() > WIDTH {
             return;
         }
         for row in &mut self.rows {
@@ -125,8 +160,9 @@ impl Shape {
                 *row >>= amount;
             }
         }
-        self.first_col += amount;
+        self.first_col = first_col;
         self.last_col += amount;
+        debug_assert_eq!((self.first_col, self.last_col), Self::cols(&self.rows));
     }
 }
 
@@ -175,43 +211,96 @@ fn parse(input: &str) -> impl Iterator<Item = (usize, i8)> + '_ {
         .cycle()
 }
 
-fn compute(input: &str, mut count: usize) -> usize {
-    let mut dirs = parse(input);
-    let mut shapes = spawn_shapes();
-    let mut board = Board::new();
-
-    let mut looped_height = 0;
-
+// How far `cycle_info` looks for a cycle before giving up
+const CYCLE_SEARCH_CAP: usize = 100_000;
+
+// Drops up to `max_steps` rocks, stopping early once the board starts repeating.
+// The height after each rock is appended to `heights`.
+fn find_cycle(
+    board: &mut Board,
+    dirs: &mut impl Iterator<Item = (usize, i8)>,
+    shapes: &mut impl Iterator<Item = (usize, Shape)>,
+    max_steps: usize,
+    heights: &mut Vec<usize>,
+) -> Option<CycleInfo> {
     #[derive(PartialEq, Eq, PartialOrd, Ord)]
     struct Key {
         dir_index: usize,
         shape_index: usize,
         ceiling: [u8; 4],
     }
-    let mut cache = BTreeMap::<Key, (usize, usize)>::new();
-
-    for iteration in 0..count {
-        let (shape_index, dir_index) = board.play_single_iteration(&mut dirs, &mut shapes);
-        count -= 1;
-        let ceiling = match board.impassable_ceiling() {
-            None => continue,
-            Some(ceiling) => ceiling,
-        };
+    // Only a sealed ceiling makes the state below it irrelevant
+    detect_cycle(max_steps, || {
+        let (shape_index, dir_index) = board.play_single_iteration(dirs, shapes);
+        heights.push(board.height());
         let key = Key {
             shape_index,
             dir_index,
-            ceiling,
+            ceiling: board.impassable_ceiling()?,
         };
-        if let Some(&(prev_iteration, prev_height)) = cache.get(&key) {
-            let cycle_length = iteration - prev_iteration;
-            let gained_height = board.height() - prev_height;
-            looped_height = (count / cycle_length) * gained_height;
-            count %= cycle_length;
-            break;
+        Some((key, board.height()))
+    })
+}
+
+// (rocks dropped before the cycle starts, rocks per cycle, height gained per cycle)
+fn cycle_info(input: &str) -> Option<(usize, usize, usize)> {
+    let cycle = find_cycle(
+        &mut Board::new(),
+        &mut parse(input),
+        &mut spawn_shapes(),
+        CYCLE_SEARCH_CAP,
+        &mut Vec::new(),
+    )?;
+    Some((cycle.offset + 1, cycle.length, cycle.gain))
+}
+
+// The heights seen while looking for a cycle, which together with the cycle
+// give the height after any number of rocks without simulating again
+struct Simulator {
+    // Indexed by the number of rocks dropped
+    heights: Vec<usize>,
+    cycle: Option<CycleInfo>,
+}
+
+impl Simulator {
+    fn new(input: &str, max_steps: usize) -> Self {
+        let mut heights = vec![0];
+        let cycle = find_cycle(
+            &mut Board::new(),
+            &mut parse(input),
+            &mut spawn_shapes(),
+            max_steps,
+            &mut heights,
+        );
+        Self { heights, cycle }
+    }
+
+    // `None` if `count` is past the simulated rocks and no cycle was found
+    fn height_after(&self, count: usize) -> Option<usize> {
+        if let Some(&height) = self.heights.get(count) {
+            return Some(height);
         }
-        cache.insert(key, (iteration, board.height()));
+        let cycle = self.cycle.as_ref()?;
+        // The board after `offset + 1` rocks repeats every `length` rocks
+        let start = cycle.offset + 1;
+        let (looped_height, leftover) = cycle.skip(count - start);
+        Some(self.heights[start + leftover] + looped_height)
     }
+}
 
+// Simulates every rock, skipping ahead only once a cycle is found; `Simulator`
+// is checked against this
+#[cfg(test)]
+fn compute(input: &str, count: usize) -> usize {
+    let mut dirs = parse(input);
+    let mut shapes = spawn_shapes();
+    let mut board = Board::new();
+
+    let cycle = find_cycle(&mut board, &mut dirs, &mut shapes, count, &mut Vec::new());
+    let (looped_height, count) = match cycle {
+        Some(cycle) => cycle.skip(count - cycle.steps_taken()),
+        None => (0, 0),
+    };
     for _ in 0..count {
         board.play_single_iteration(&mut dirs, &mut shapes);
     }
@@ -219,12 +308,25 @@ fn compute(input: &str, mut count: usize) -> usize {
     board.height() + looped_height
 }
 
+pub(crate) fn report(input: &str) -> String {
+    match cycle_info(input) {
+        Some((prefix, length, gain)) => {
+            format!("After {prefix} rocks, every {length} rocks add {gain} rows")
+        }
+        None => format!("No cycle within {CYCLE_SEARCH_CAP} rocks"),
+    }
+}
+
 pub(crate) fn solve(input: &str) -> usize {
-    compute(input, 2022)
+    Simulator::new(input, CYCLE_SEARCH_CAP)
+        .height_after(2022)
+        .expect("No cycle found")
 }
 
 pub(crate) fn solve_2(input: &str) -> usize {
-    compute(input, 1000000000000)
+    Simulator::new(input, CYCLE_SEARCH_CAP)
+        .height_after(1000000000000)
+        .expect("No cycle found")
 }
 
 #[cfg(test)]
@@ -304,6 +406,46 @@ mod tests {
         assert_eq!(board.intersects(&shape2, 2), false);
     }
 
+    #[test]
+    fn test_from_rows() {
+        assert!(Board::from_rows(vec![0b11111111]).is_err());
+        assert!(Board::from_rows(vec![0b11111110, 0]).is_err());
+        let mut board = Board::from_rows(vec![0b11111110]).unwrap();
+        let mut dirs = parse(">");
+        let mut shapes = spawn_shapes();
+        board.play_single_iteration(&mut dirs, &mut shapes);
+        assert_eq!(board.height(), 2);
+        assert_eq!(board.rows, vec![0b11111110, 0b00011110]);
+    }
+
+    #[test]
+    fn test_top_rows() {
+        let rows = vec![0b11110000, 0b00100000, 0b01110000];
+        let board = Board::from_rows(rows.clone()).unwrap();
+        assert_eq!(board.top_rows(0), &[]);
+        assert_eq!(board.top_rows(2), &[0b00100000, 0b01110000]);
+        assert_eq!(board.top_rows(3), &rows[..]);
+        assert_eq!(board.top_rows(10), &rows[..]);
+        assert_eq!(Board::new().top_rows(4), &[]);
+    }
+
+    #[test]
+    fn test_shape_dimensions() {
+        let dimensions = spawn_shapes()
+            .take(5)
+            .map(|(_, shape)| (shape.width(), shape.height()))
+            .collect_vec();
+        assert_eq!(dimensions, vec![(4, 1), (3, 3), (3, 3), (1, 4), (2, 2)]);
+        for (_, mut shape) in spawn_shapes().take(5) {
+            let width = shape.width();
+            for amount in [-3, 1, 1, 1, 1, 5] {
+                shape.shift(amount);
+                assert_eq!(shape.width(), width);
+                assert!(shape.first_col >= 0 && shape.last_col < WIDTH);
+            }
+        }
+    }
+
     #[test]
     fn test_bug() {
         /*
@@ -322,13 +464,44 @@ mod tests {
         assert_eq!(shape.last_col, 4);
     }
 
+    #[test]
+    fn test_cycle_info() {
+        let (prefix, length, gain) = cycle_info(EXAMPLE).unwrap();
+        assert_eq!((length, gain), (35, 53));
+        assert_eq!(cycle_info(EXAMPLE), Some((prefix, length, gain)));
+        assert_eq!(
+            report(EXAMPLE),
+            format!("After {prefix} rocks, every 35 rocks add 53 rows")
+        );
+        for cycles in 1..4 {
+            let looped = compute(EXAMPLE, prefix + cycles * length);
+            assert_eq!(looped - compute(EXAMPLE, prefix), cycles * gain);
+        }
+    }
+
+    #[test]
+    fn test_height_after() {
+        let simulator = Simulator::new(EXAMPLE, CYCLE_SEARCH_CAP);
+        assert_eq!(simulator.height_after(2022), Some(3068));
+        assert_eq!(simulator.height_after(1000000000000), Some(1514285714288));
+        for count in [0, 1, 10, 100, 500, 1000, 2022, 5000] {
+            assert_eq!(simulator.height_after(count), Some(compute(EXAMPLE, count)));
+        }
+        // Too few rocks to find the cycle, so only those heights are known
+        let simulator = Simulator::new(EXAMPLE, 10);
+        assert_eq!(simulator.height_after(10), Some(compute(EXAMPLE, 10)));
+        assert_eq!(simulator.height_after(11), None);
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(compute(EXAMPLE, 2022), 3068);
+        assert_eq!(solve(EXAMPLE), 3068);
     }
 
     #[test]
     fn test_solve_2() {
         assert_eq!(compute(EXAMPLE, 1000000000000), 1514285714288);
+        assert_eq!(solve_2(EXAMPLE), 1514285714288);
     }
 }
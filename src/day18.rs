@@ -2,6 +2,8 @@ use std::collections::{BTreeSet, HashSet};
 
 use itertools::Itertools;
 
+use crate::utils::{clean_lines, parse_ints, ParseError};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Cube {
     x: i8,
@@ -48,6 +50,18 @@ impl BBox {
         self.max.z = self.max.z.max(cube.z + 1);
     }
 
+    #[cfg(test)]
+    fn touches(&self, cube: Cube) -> bool {
+        cube.x == self.min.x
+            || cube.y == self.min.y
+            || cube.z == self.min.z
+            || cube.x + 1 == self.max.x
+            || cube.y + 1 == self.max.y
+            || cube.z + 1 == self.max.z
+    }
+
+    // One extra layer on every side, so the empty cells around the shape are always
+    // connected, even where a cavity opens right at a corner of the bbox
     fn loosely_contains(&self, cube: Cube) -> bool {
         cube.x >= self.min.x - 1
             && cube.y >= self.min.y - 1
@@ -73,6 +87,19 @@ impl Droplet {
         }
     }
 
+    fn from_cubes(cubes: impl Iterator<Item = Cube>) -> Self {
+        let mut droplet = Self::new();
+        for cube in cubes {
+            droplet.add_cube(cube);
+        }
+        droplet
+    }
+
+    // Min and max corners; the max corner is exclusive
+    fn bbox(&self) -> Option<(Cube, Cube)> {
+        self.bbox.as_ref().map(|bbox| (bbox.min, bbox.max))
+    }
+
     fn add_cube(&mut self, cube: Cube) {
         self.cubes.insert(cube);
         self.total_surface_area += 6;
@@ -90,10 +117,54 @@ impl Droplet {
         };
     }
 
+    #[cfg(test)]
+    fn remove_cube(&mut self, cube: Cube) -> bool {
+        if !self.cubes.remove(&cube) {
+            return false;
+        }
+        self.total_surface_area -= 6;
+        for adj_cube in cube.adjacent_cubes() {
+            if self.cubes.contains(&adj_cube) {
+                self.total_surface_area += 2;
+            }
+        }
+        // Only a cube on the edge of the bbox can shrink it
+        if self.bbox.as_ref().is_some_and(|bbox| bbox.touches(cube)) {
+            self.bbox = self.cubes.iter().fold(None, |bbox, &cube| match bbox {
+                None => Some(BBox::new(cube)),
+                Some(mut bbox) => {
+                    bbox.extend(cube);
+                    Some(bbox)
+                }
+            });
+        }
+        true
+    }
+
     fn exterior_surface_area(&self) -> usize {
         let Some(bbox) = &self.bbox else {
             return 0;
         };
+        self.flood_outside(bbox).1
+    }
+
+    // Empty cells enclosed by the droplet: everything in the bbox that the outside
+    // flood fill couldn't reach
+    fn interior_cubes(&self) -> BTreeSet<Cube> {
+        let Some(bbox) = &self.bbox else {
+            return BTreeSet::new();
+        };
+        let (outside, _) = self.flood_outside(bbox);
+        (bbox.min.x..bbox.max.x)
+            .cartesian_product(bbox.min.y..bbox.max.y)
+            .cartesian_product(bbox.min.z..bbox.max.z)
+            .map(|((x, y), z)| Cube::new(x, y, z))
+            .filter(|cube| !self.cubes.contains(cube) && !outside.contains(cube))
+            .collect()
+    }
+
+    // Empty cells reachable from outside the droplet, and how many cube faces they touch
+    fn flood_outside(&self, bbox: &BBox) -> (HashSet<Cube>, usize) {
         let mut queue: Vec<_> = [bbox.max].into_iter().collect();
         let mut seen: HashSet<_> = [bbox.max].into_iter().collect();
         let mut result = 0;
@@ -106,35 +177,81 @@ impl Droplet {
                 }
             }
         }
-        result
+        (seen, result)
+    }
+}
+
+impl Droplet {
+    // One x-y slice per z level, `#` for lava, `o` for trapped air and `.` for the rest
+    fn cross_sections(&self, interior: &BTreeSet<Cube>) -> String {
+        let Some((min, max)) = self.bbox() else {
+            return String::new();
+        };
+        (min.z..max.z)
+            .map(|z| {
+                let rows = (min.y..max.y)
+                    .map(|y| {
+                        (min.x..max.x)
+                            .map(|x| {
+                                let cube = Cube::new(x, y, z);
+                                if self.cubes.contains(&cube) {
+                                    '#'
+                                } else if interior.contains(&cube) {
+                                    'o'
+                                } else {
+                                    '.'
+                                }
+                            })
+                            .collect::<String>()
+                    })
+                    .join("\n");
+                format!("z = {z}\n{rows}")
+            })
+            .join("\n\n")
     }
 }
 
-fn parse(input: &str) -> impl Iterator<Item = Cube> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .flat_map(|l| l.split(','))
-        .map(|s| s.parse().unwrap())
-        .tuples()
-        .map(|(x, y, z)| Cube::new(x, y, z))
+const EXPECTED: &str = "`<x>,<y>,<z>` with each coordinate in -128..=127";
+
+fn parse(input: &str) -> impl Iterator<Item = Result<Cube, ParseError>> + '_ {
+    clean_lines(input).map(|l| {
+        let error = || ParseError::new(l, EXPECTED);
+        // `parse_ints` skips whatever is between the numbers, so check for junk here
+        let is_junk = |c: char| !(c.is_ascii_digit() || c == '-' || c == ',');
+        if l.chars().any(is_junk) {
+            return Err(error());
+        }
+        let ints = parse_ints(l).map_err(|_| error())?;
+        let coord = |n: isize| i8::try_from(n).map_err(|_| error());
+        match ints[..] {
+            [x, y, z] => Ok(Cube::new(coord(x)?, coord(y)?, coord(z)?)),
+            _ => Err(error()),
+        }
+    })
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    let mut droplet = Droplet::new();
-    for cube in parse(input) {
-        droplet.add_cube(cube);
-    }
-    droplet.total_surface_area
+fn parse_droplet(input: &str) -> Result<Droplet, ParseError> {
+    let cubes = parse(input).collect::<Result<Vec<_>, _>>()?;
+    Ok(Droplet::from_cubes(cubes.into_iter()))
 }
 
-pub(crate) fn solve_2(input: &str) -> usize {
-    let mut droplet = Droplet::new();
-    for cube in parse(input) {
-        droplet.add_cube(cube);
+fn interior_cubes(input: &str) -> Result<BTreeSet<Cube>, ParseError> {
+    Ok(parse_droplet(input)?.interior_cubes())
+}
+
+pub(crate) fn visualize(input: &str) -> String {
+    match (parse_droplet(input), interior_cubes(input)) {
+        (Ok(droplet), Ok(interior)) => droplet.cross_sections(&interior),
+        (Err(e), _) | (_, Err(e)) => e.to_string(),
     }
-    droplet.exterior_surface_area()
+}
+
+pub(crate) fn solve(input: &str) -> Result<usize, ParseError> {
+    Ok(parse_droplet(input)?.total_surface_area)
+}
+
+pub(crate) fn solve_2(input: &str) -> Result<usize, ParseError> {
+    Ok(parse_droplet(input)?.exterior_surface_area())
 }
 
 #[cfg(test)]
@@ -158,13 +275,125 @@ mod tests {
         2,3,5
     ";
 
+    #[test]
+    fn test_from_cubes() {
+        let block = (0..2)
+            .cartesian_product(0..2)
+            .cartesian_product(0..2)
+            .map(|((x, y), z)| Cube::new(x + 1, y - 1, z));
+        let droplet = Droplet::from_cubes(block);
+        assert_eq!(droplet.total_surface_area, 24);
+        assert_eq!(droplet.exterior_surface_area(), 24);
+        assert_eq!(
+            droplet.bbox(),
+            Some((Cube::new(1, -1, 0), Cube::new(3, 1, 2)))
+        );
+        assert_eq!(Droplet::new().bbox(), None);
+    }
+
+    #[test]
+    fn test_visualize() {
+        assert_eq!(
+            visualize("1,1,1\n2,1,1\n1,2,2"),
+            "z = 1\n##\n..\n\nz = 2\n..\n#."
+        );
+        assert_eq!(visualize(""), "");
+        let slice = visualize(EXAMPLE).split("\n\n").nth(4).unwrap().to_owned();
+        assert_eq!(slice, "z = 5\n.#.\n#o#\n.#.");
+    }
+
+    #[test]
+    fn test_remove_cube() {
+        let a = Cube::new(0, 0, 0);
+        let b = Cube::new(1, 0, 0);
+        let c = Cube::new(2, 0, 0);
+        let mut droplet = Droplet::from_cubes([a, b, c].into_iter());
+        assert_eq!(droplet.total_surface_area, 14);
+        assert!(droplet.remove_cube(b));
+        assert!(!droplet.remove_cube(b));
+        let fresh = Droplet::from_cubes([a, c].into_iter());
+        assert_eq!(droplet.total_surface_area, fresh.total_surface_area);
+        assert_eq!(droplet.exterior_surface_area(), 12);
+
+        assert!(droplet.remove_cube(c));
+        assert_eq!(droplet.bbox(), Some((a, Cube::new(1, 1, 1))));
+        assert!(droplet.remove_cube(a));
+        assert_eq!(droplet.total_surface_area, 0);
+        assert_eq!(droplet.bbox(), None);
+    }
+
+    #[test]
+    fn test_interior_cubes() {
+        assert_eq!(interior_cubes(EXAMPLE), Ok([Cube::new(2, 2, 5)].into()));
+        assert_eq!(interior_cubes("1,1,1"), Ok(BTreeSet::new()));
+        assert_eq!(interior_cubes(""), Ok(BTreeSet::new()));
+
+        // A hollow 3x3x3 shell encloses just its centre
+        let shell = (0..3)
+            .cartesian_product(0..3)
+            .cartesian_product(0..3)
+            .map(|((x, y), z)| Cube::new(x, y, z))
+            .filter(|&cube| cube != Cube::new(1, 1, 1));
+        let droplet = Droplet::from_cubes(shell);
+        assert_eq!(droplet.interior_cubes(), [Cube::new(1, 1, 1)].into());
+    }
+
+    #[test]
+    fn test_corner_cavity() {
+        let block = || {
+            (0..3)
+                .cartesian_product(0..3)
+                .cartesian_product(0..3)
+                .map(|((x, y), z)| Cube::new(x, y, z))
+        };
+        // Tunnels from the centre out through each extreme corner
+        let tunnels = [
+            [(1, 1, 1), (2, 1, 1), (2, 2, 1), (2, 2, 2)],
+            [(1, 1, 1), (0, 1, 1), (0, 0, 1), (0, 0, 0)],
+        ];
+        for tunnel in tunnels {
+            let tunnel = tunnel.map(|(x, y, z)| Cube::new(x, y, z));
+            let droplet = Droplet::from_cubes(block().filter(|cube| !tunnel.contains(cube)));
+            assert_eq!(droplet.interior_cubes(), BTreeSet::new());
+            assert_eq!(droplet.exterior_surface_area(), droplet.total_surface_area);
+            // 48 faces left on the outside plus 12 lining the tunnel
+            assert_eq!(droplet.exterior_surface_area(), 60);
+        }
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(solve(EXAMPLE), 64);
+        assert_eq!(solve(EXAMPLE), Ok(64));
+    }
+
+    #[test]
+    fn test_parse_negative() {
+        let cubes: Vec<_> = parse("-1,0,2\n0,-3,-2").collect();
+        assert_eq!(
+            cubes,
+            vec![Ok(Cube::new(-1, 0, 2)), Ok(Cube::new(0, -3, -2))]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for line in ["300,1,2", "1,2", "1,2,3,4", "1,a2,3", "1,2,-129"] {
+            assert_eq!(
+                parse(line).collect_vec(),
+                vec![Err(ParseError::new(line, EXPECTED))],
+                "{line}"
+            );
+        }
+        // A bad line no longer shifts the cubes after it
+        assert!(solve("1,2,3\n4,5\n6,7,8").is_err());
+        assert_eq!(
+            parse("127,-128,0").next(),
+            Some(Ok(Cube::new(127, -128, 0)))
+        );
     }
 
     #[test]
     fn test_solve_2() {
-        assert_eq!(solve_2(EXAMPLE), 58);
+        assert_eq!(solve_2(EXAMPLE), Ok(58));
     }
 }
@@ -1,31 +1,47 @@
-use std::{collections::HashMap, iter::zip};
+use std::iter::zip;
 
 use itertools::Itertools;
 use regex::Regex;
 
+use crate::utils::{clean_lines, DpStats, MemoStats, MemoTable, ParseError};
+
 struct Robot {
     costs: [u8; 3],
     _produces: usize,
 }
 
 struct Blueprint {
+    id: usize,
     robots: [Robot; 4],
     caps: [u8; 3],
 }
 
-fn parse(input: &str) -> impl Iterator<Item = Blueprint> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+impl Blueprint {
+    // The most of each resource any robot costs. Only one robot is built per
+    // minute, so more than `caps[i]` robots of resource `i` are never useful.
+    fn caps(&self) -> [u8; 3] {
+        self.caps
+    }
+}
+
+const EXPECTED: &str = "a blueprint id followed by six robot costs below 256";
+
+fn parse(input: &str) -> impl Iterator<Item = Result<Blueprint, ParseError>> + '_ {
+    clean_lines(input)
         .map(|l| {
             let re = Regex::new(r"\d+").unwrap();
-            let (_, ore_ore, clay_ore, obs_ore, obs_clay, geo_ore, geo_obs) = re
-                .find_iter(l)
-                .map(|m| m.as_str().parse().unwrap())
-                .collect_tuple()
-                .unwrap();
-            Blueprint {
+            let error = || ParseError::new(l, EXPECTED);
+            let mut numbers = re.find_iter(l).map(|m| m.as_str());
+            // The blueprint id isn't a cost, so it isn't limited to a u8
+            let id = numbers.next().ok_or_else(error)?;
+            let id = id.parse::<usize>().map_err(|_| error())?;
+            let numbers = numbers
+                .map(|n| n.parse::<u8>().map_err(|_| error()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (ore_ore, clay_ore, obs_ore, obs_clay, geo_ore, geo_obs) =
+                numbers.into_iter().collect_tuple().ok_or_else(error)?;
+            Ok(Blueprint {
+                id,
                 robots: [
                     Robot {
                         costs: [ore_ore, 0, 0],
@@ -49,7 +65,7 @@ fn parse(input: &str) -> impl Iterator<Item = Blueprint> + '_ {
                     obs_clay,
                     geo_obs,
                 ],
-            }
+            })
         })
 }
 
@@ -60,35 +76,73 @@ struct State {
     budget: i8,
 }
 
-fn compute(minutes: i8, blueprint: Blueprint) -> usize {
-    fn recurse(memo: &mut HashMap<State, usize>, blueprint: &Blueprint, state: State) -> usize {
+// Replays one optional robot build per minute, from the same start as compute
+#[cfg(test)]
+fn simulate_schedule(
+    minutes: i8,
+    blueprint: &Blueprint,
+    choices: &[Option<usize>],
+) -> Result<State, String> {
+    if choices.len() > minutes as usize {
+        return Err(format!("{} choices for {minutes} minutes", choices.len()));
+    }
+    let mut state = State {
+        robots: [1, 0, 0, 0],
+        resources: [0, 0, 0, 0],
+        budget: minutes,
+    };
+    for minute in 0..minutes as usize {
+        let choice = choices.get(minute).copied().flatten();
+        if let Some(build_index) = choice {
+            let costs = &blueprint
+                .robots
+                .get(build_index)
+                .ok_or_else(|| format!("No robot {build_index}"))?
+                .costs;
+            if zip(&state.resources, costs).any(|(a, b)| a < b) {
+                return Err(format!(
+                    "Robot {build_index} is unaffordable in minute {}",
+                    minute + 1
+                ));
+            }
+            zip(&mut state.resources, costs).for_each(|(a, b)| *a -= b);
+        }
+        zip(&mut state.resources, state.robots).for_each(|(a, b)| *a += b);
+        if let Some(build_index) = choice {
+            state.robots[build_index] += 1;
+        }
+        state.budget -= 1;
+    }
+    Ok(state)
+}
+
+fn compute(minutes: i8, blueprint: Blueprint) -> (usize, MemoStats) {
+    fn recurse(memo: &mut MemoTable<State, usize>, blueprint: &Blueprint, state: State) -> usize {
         if state.budget == 0 {
             return state.resources[3] as usize;
         }
-        if zip(state.robots, blueprint.caps).any(|(a, b)| a > b) {
+        if zip(state.robots, blueprint.caps()).any(|(a, b)| a > b) {
             return state.resources[3] as usize;
         }
-        if let Some(&result) = memo.get(&state) {
-            return result;
-        }
-        let mut best_score = state.resources[3] as usize;
-        for build_index in (0..state.robots.len()).rev() {
-            let costs = &blueprint.robots[build_index].costs;
-            let affordable = zip(&state.resources, costs).all(|(a, b)| a >= b);
-            let mut state = state.clone();
-            zip(&mut state.resources, state.robots).for_each(|(a, b)| *a += b);
-            if affordable {
-                zip(&mut state.resources, costs).for_each(|(a, b)| *a -= b);
-                state.robots[build_index] += 1;
-            }
-            state.budget -= 1;
-            best_score = best_score.max(recurse(memo, blueprint, state));
-            if affordable && build_index == 3 {
-                break;
+        memo.get_or_compute(state, |memo| {
+            let mut best_score = state.resources[3] as usize;
+            for build_index in (0..state.robots.len()).rev() {
+                let costs = &blueprint.robots[build_index].costs;
+                let affordable = zip(&state.resources, costs).all(|(a, b)| a >= b);
+                let mut state = state.clone();
+                zip(&mut state.resources, state.robots).for_each(|(a, b)| *a += b);
+                if affordable {
+                    zip(&mut state.resources, costs).for_each(|(a, b)| *a -= b);
+                    state.robots[build_index] += 1;
+                }
+                state.budget -= 1;
+                best_score = best_score.max(recurse(memo, blueprint, state));
+                if affordable && build_index == 3 {
+                    break;
+                }
             }
-        }
-        memo.insert(state, best_score);
-        best_score
+            best_score
+        })
     }
 
     let initial_state = State {
@@ -96,19 +150,66 @@ fn compute(minutes: i8, blueprint: Blueprint) -> usize {
         resources: [0, 0, 0, 0],
         budget: minutes,
     };
-    let mut memo = HashMap::new();
-    recurse(&mut memo, &blueprint, initial_state)
+    let mut memo = MemoTable::default();
+    let result = recurse(&mut memo, &blueprint, initial_state);
+    (result, memo.dp_stats())
+}
+
+pub(crate) fn parse_count(input: &str) -> Result<usize, ParseError> {
+    parse(input).collect::<Result<Vec<_>, _>>().map(|blueprints| blueprints.len())
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    parse(input)
-        .enumerate()
-        .map(|(i, bp)| (i + 1) * compute(24, bp))
-        .sum()
+// (blueprint id, geodes opened in `minutes`) for every blueprint
+fn geodes_with_stats(
+    input: &str,
+    minutes: i8,
+) -> Result<(Vec<(usize, usize)>, MemoStats), ParseError> {
+    let mut stats = MemoStats::default();
+    let geodes = parse(input)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|bp| {
+            let id = bp.id;
+            let (geodes, bp_stats) = compute(minutes, bp);
+            stats += bp_stats;
+            (id, geodes)
+        })
+        .collect();
+    Ok((geodes, stats))
 }
 
-pub(crate) fn solve_2(input: &str) -> usize {
-    parse(input).take(3).map(|bp| compute(32, bp)).product()
+fn quality_scores(input: &str, minutes: i8) -> Result<Vec<(usize, usize)>, ParseError> {
+    geodes_with_stats(input, minutes).map(|(geodes, _)| geodes)
+}
+
+pub(crate) fn solve_with_stats(input: &str) -> Result<(usize, MemoStats), ParseError> {
+    let (geodes, stats) = geodes_with_stats(input, 24)?;
+    let result = geodes.into_iter().map(|(id, geodes)| id * geodes).sum();
+    Ok((result, stats))
+}
+
+pub(crate) fn solve_2_with_stats(input: &str) -> Result<(usize, MemoStats), ParseError> {
+    let mut stats = MemoStats::default();
+    let result = parse(input)
+        .take(3)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|bp| {
+            let (geodes, bp_stats) = compute(32, bp);
+            stats += bp_stats;
+            geodes
+        })
+        .product();
+    Ok((result, stats))
+}
+
+pub(crate) fn solve(input: &str) -> Result<usize, ParseError> {
+    let geodes = quality_scores(input, 24)?;
+    Ok(geodes.into_iter().map(|(id, geodes)| id * geodes).sum())
+}
+
+pub(crate) fn solve_2(input: &str) -> Result<usize, ParseError> {
+    solve_2_with_stats(input).map(|(result, _)| result)
 }
 
 #[cfg(test)]
@@ -126,7 +227,8 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let blueprint = parse(EXAMPLE).next().unwrap();
+        let blueprint = parse(EXAMPLE).next().unwrap().unwrap();
+        assert_eq!(blueprint.id, 1);
         assert_eq!(blueprint.robots[0].costs, [4, 0, 0]);
         assert_eq!(blueprint.robots[1].costs, [2, 0, 0]);
         assert_eq!(blueprint.robots[2].costs, [3, 14, 0]);
@@ -137,13 +239,79 @@ mod tests {
         assert_eq!(blueprint.robots[3]._produces, 3);
     }
 
+    #[test]
+    fn test_caps() {
+        let blueprint = parse(EXAMPLE).next().unwrap().unwrap();
+        assert_eq!(blueprint.caps(), [4, 14, 7]);
+        let blueprint = parse("Blueprint 2: 2 3 3 8 3 12").next().unwrap().unwrap();
+        assert_eq!(blueprint.caps(), [3, 8, 12]);
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        let missing = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore.";
+        assert_eq!(parse_count(missing), Err(ParseError::new(missing, EXPECTED)));
+        let too_costly = "Blueprint 1: 4 2 3 14 2 700";
+        assert_eq!(
+            solve(too_costly).unwrap_err().to_string(),
+            format!("couldn't parse {too_costly:?}, expected {EXPECTED}")
+        );
+        assert_eq!(parse_count("Blueprint 1000: 4 2 3 14 2 7"), Ok(1));
+    }
+
+    #[test]
+    fn test_simulate_schedule() {
+        let blueprint = parse(EXAMPLE).next().unwrap().unwrap();
+        // The plan from the puzzle description, by minute
+        let mut choices = vec![None; 24];
+        let plan = [(3, 1), (5, 1), (7, 1), (11, 2), (12, 1), (15, 2), (18, 3), (21, 3)];
+        for (minute, robot) in plan {
+            choices[minute - 1] = Some(robot);
+        }
+        let state = simulate_schedule(24, &blueprint, &choices).unwrap();
+        assert_eq!(state.robots, [1, 4, 2, 2]);
+        assert_eq!(state.resources, [6, 41, 8, 9]);
+        assert_eq!(state.budget, 0);
+
+        choices[2] = Some(0);
+        assert!(simulate_schedule(24, &blueprint, &choices).is_err());
+        assert!(simulate_schedule(2, &blueprint, &choices).is_err());
+        let idle = simulate_schedule(2, &blueprint, &[]).unwrap();
+        assert_eq!(idle.resources, [2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_solve_uses_ids() {
+        // Only geode robots are affordable, at one ore each, so one is built every
+        // minute after the first: 22 + 21 + ... + 1 geodes
+        let input = "Blueprint 5: 200 200 200 200 1 0";
+        assert_eq!(quality_scores(input, 24), Ok(vec![(5, 253)]));
+        assert_eq!(solve(input), Ok(5 * 253));
+    }
+
+    #[test]
+    fn test_quality_scores() {
+        // Ids come from the input, not the order of the blueprints
+        let input = format!(
+            "{}
+            Blueprint 2: \
+                Each ore robot costs 2 ore. \
+                Each clay robot costs 3 ore. \
+                Each obsidian robot costs 3 ore and 8 clay. \
+                Each geode robot costs 3 ore and 12 obsidian.",
+            EXAMPLE.replace("Blueprint 1:", "Blueprint 7:")
+        );
+        assert_eq!(quality_scores(&input, 24), Ok(vec![(7, 9), (2, 12)]));
+        assert_eq!(quality_scores("", 24), Ok(vec![]));
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(solve(EXAMPLE), 9);
+        assert_eq!(solve(EXAMPLE), Ok(9));
     }
 
     #[test]
     fn test_solve_2() {
-        assert_eq!(solve_2(EXAMPLE), 56);
+        assert_eq!(solve_2(EXAMPLE), Ok(56));
     }
 }
@@ -1,5 +1,7 @@
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Move {
     Paper,
@@ -61,10 +63,7 @@ impl Move {
 }
 
 fn parse_str_tuples(input: &str) -> impl Iterator<Item = (&str, &str)> {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .map(|l| l.split_ascii_whitespace().collect_tuple().unwrap())
 }
 
@@ -83,8 +82,16 @@ fn parse_2(input: &str) -> impl Iterator<Item = (Move, Move)> + '_ {
         .map(|(l, r)| (l, Move::find(l, r)))
 }
 
+// (shape bonuses, outcome points) making up the part 1 score
+fn score_breakdown(input: &str) -> (i32, i32) {
+    parse(input).fold((0, 0), |(bonuses, outcomes), (l, r)| {
+        (bonuses + r.bonus(), outcomes + r.score(l))
+    })
+}
+
 pub(crate) fn solve(input: &str) -> i32 {
-    parse(input).map(|(l, r)| r.score(l) + r.bonus()).sum()
+    let (bonuses, outcomes) = score_breakdown(input);
+    bonuses + outcomes
 }
 
 pub(crate) fn solve_2(input: &str) -> i32 {
@@ -99,6 +106,7 @@ mod tests {
     fn test_parse() {
         let result: Vec<_> = parse(
             "
+            # opponent response
             A X
             B Y
             C Z
@@ -154,4 +162,15 @@ mod tests {
     fn test_full() {
         assert_eq!(solve("B Z"), 9);
     }
+
+    #[test]
+    fn test_score_breakdown() {
+        let input = "
+            A Y
+            B X
+            C Z
+        ";
+        assert_eq!(score_breakdown(input), (6, 9));
+        assert_eq!(solve(input), 15);
+    }
 }
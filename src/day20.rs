@@ -7,6 +7,8 @@ use std::{
 
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 #[derive(Debug)]
 struct Node {
     value: isize,
@@ -54,10 +56,7 @@ struct List {
 
 impl Debug for List {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let nodes = Self::iter(Direction::Forwards, self.zero.clone())
-            .map(|n| n.borrow().value)
-            .take(self.nodes.len());
-        f.debug_list().entries(nodes).finish()
+        f.debug_list().entries(self.to_vec()).finish()
     }
 }
 
@@ -91,6 +90,14 @@ impl List {
         Self { zero, nodes }
     }
 
+    // Values in list order, starting from zero
+    fn to_vec(&self) -> Vec<isize> {
+        Self::iter(Direction::Forwards, self.zero.clone())
+            .map(|n| n.borrow().value)
+            .take(self.nodes.len())
+            .collect()
+    }
+
     fn scale(&self, factor: isize) {
         for node in &self.nodes {
             node.borrow_mut().value *= factor;
@@ -133,16 +140,36 @@ impl List {
         self.shift(node, offset);
     }
 
+    // Mixes the node that started at `original_index`, returning the list from zero
+    #[cfg(test)]
+    fn mix_step(&mut self, original_index: usize) -> Vec<isize> {
+        self.mix(self.nodes[original_index].clone());
+        self.to_vec()
+    }
+
     fn iter(dir: Direction, node: Rc<RefCell<Node>>) -> impl Iterator<Item = Rc<RefCell<Node>>> {
         NodeIter { node, dir }
     }
+
+    // Unlinks every node and releases the list's strong references. The links are
+    // all `Weak` today, but this keeps drops leak-free if that ever changes.
+    fn clear(&mut self) {
+        for node in self.nodes.drain(..) {
+            let mut node = node.borrow_mut();
+            node.prev = Weak::new();
+            node.next = Weak::new();
+        }
+    }
+}
+
+impl Drop for List {
+    fn drop(&mut self) {
+        self.clear();
+    }
 }
 
 fn parse(input: &str) -> impl Iterator<Item = isize> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .map(|l| l.parse().unwrap())
 }
 
@@ -204,16 +231,83 @@ mod tests {
 
     #[test]
     fn test_shifty() {
-        let test = |a: &[isize], offset, b: &[isize]| {
+        // Shifts the node at `index` and compares the list starting from zero
+        let test = |a: &[isize], index: usize, offset, b: &[isize]| {
             let mut l = List::new(a.iter().cloned());
+            l.shift(l.nodes[index].clone(), offset);
+            assert_eq!(l.to_vec(), b, "{a:?} shifting {index} by {offset}");
+        };
+        test(&[0, 1, 2], 0, 1, &[0, 2, 1]);
+        test(&[0, 1, 2], 0, 2, &[0, 1, 2]);
+        test(&[0, 1, 2], 0, 3, &[0, 2, 1]);
+        test(&[0, 1, 2], 0, -1, &[0, 2, 1]);
+        test(&[0, 1, 2, 3, 4], 0, 3, &[0, 4, 1, 2, 3]);
+        test(&[0, 1, 2, 3, 4], 1, -1, &[0, 2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn test_large_shifts() {
+        let test = |offset, b: &[isize]| {
+            let mut l = List::new(0..5);
             l.shift(l.zero.clone(), offset);
-            assert_eq!(&l, &List::new(b.iter().cloned()));
+            assert_eq!(l.to_vec(), b, "shifting by {offset}");
+        };
+        // With the moving node removed there are 4 gaps, so shifts wrap mod 4
+        test(11, &[0, 4, 1, 2, 3]);
+        test(-5, &[0, 4, 1, 2, 3]);
+        test(4001, &[0, 2, 3, 4, 1]);
+        test(-4001, &[0, 4, 1, 2, 3]);
+        test(-4000, &[0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_mix_step() {
+        // The walkthrough's list after each move, rotated to start at zero
+        let from_zero = |values: [isize; 7]| {
+            let zero = values.iter().position(|&v| v == 0).unwrap();
+            values[zero..]
+                .iter()
+                .chain(&values[..zero])
+                .copied()
+                .collect_vec()
         };
-        test(&[0, 1, 2], 1, &[1, 0, 2]);
-        test(&[0, 1, 2], 2, &[0, 1, 2]);
-        test(&[0, 1, 2], 3, &[1, 0, 2]);
-        test(&[0, 1, 2], -1, &[1, 0, 2]);
-        test(&[0, 1, 2, 3, 4], 3, &[1, 2, 3, 0, 4]);
+        let expected = [
+            [2, 1, -3, 3, -2, 0, 4],
+            [1, -3, 2, 3, -2, 0, 4],
+            [1, 2, 3, -2, -3, 0, 4],
+            [1, 2, -2, -3, 0, 3, 4],
+            [1, 2, -3, 0, 3, 4, -2],
+            [1, 2, -3, 0, 3, 4, -2],
+            [1, 2, -3, 4, 0, 3, -2],
+        ];
+        let mut l = List::new(parse(EXAMPLE));
+        for (i, values) in expected.into_iter().enumerate() {
+            assert_eq!(l.mix_step(i), from_zero(values), "after moving {i}");
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut l = List::new(parse(EXAMPLE));
+        let node = l.nodes[0].clone();
+        // One reference from `nodes`, one from us, none from its neighbours
+        assert_eq!(Rc::strong_count(&node), 2);
+        l.clear();
+        assert!(l.nodes.is_empty());
+        assert_eq!(Rc::strong_count(&node), 1);
+        assert!(node.borrow().next.upgrade().is_none());
+        assert!(node.borrow().prev.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_drop() {
+        let mut l = List::new(parse(EXAMPLE));
+        let nodes = l.nodes.iter().map(Rc::downgrade).collect_vec();
+        for node in l.nodes.iter().cloned().collect_vec() {
+            l.mix(node);
+        }
+        drop(l);
+        assert!(nodes.iter().all(|node| node.upgrade().is_none()));
     }
 
     #[test]
@@ -6,6 +6,8 @@ use std::{
 
 use regex::Regex;
 
+use crate::utils::{clean_lines, ParseError};
+
 #[derive(Debug, PartialEq, Eq)]
 enum Monkey<'a> {
     Immediate(isize),
@@ -146,35 +148,39 @@ fn topsort<'a>(monkeys: &HashMap<&'a str, Monkey<'a>>) -> Vec<&'a str> {
     result
 }
 
-fn parse(input: &str) -> impl Iterator<Item = (&str, Monkey)> {
+const EXPECTED: &str = "`<name>: <number>` or `<name>: <name> <+|-|*|/> <name>`";
+
+fn parse(input: &str) -> impl Iterator<Item = Result<(&str, Monkey), ParseError>> {
     let re = Regex::new(r"^(\w+): (?:(\w+) (.) (\w+)|(\d+))$").unwrap();
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .map(move |l| {
-            let cap = re.captures(l).unwrap();
+            let error = || ParseError::new(l, EXPECTED);
+            let cap = re.captures(l).ok_or_else(error)?;
             let solution = if let Some(literal) = cap.get(5) {
-                Monkey::Immediate(literal.as_str().parse().unwrap())
+                Monkey::Immediate(literal.as_str().parse().map_err(|_| error())?)
             } else {
                 let op = match &cap[3] {
                     "*" => Op::Mul,
                     "/" => Op::Div,
                     "+" => Op::Add,
                     "-" => Op::Sub,
-                    s => panic!("Unknown operation: {}", s),
+                    _ => return Err(error()),
                 };
                 let lhs = cap.get(2).unwrap().as_str();
                 let rhs = cap.get(4).unwrap().as_str();
                 Monkey::Delayed(lhs, rhs, op)
             };
             let name = cap.get(1).unwrap().as_str();
-            (name, solution)
+            Ok((name, solution))
         })
 }
 
-pub(crate) fn solve(input: &str) -> isize {
-    let monkeys: HashMap<_, _> = parse(input).collect();
+pub(crate) fn parse_count(input: &str) -> Result<usize, ParseError> {
+    parse(input).collect::<Result<Vec<_>, _>>().map(|monkeys| monkeys.len())
+}
+
+pub(crate) fn solve(input: &str) -> Result<isize, ParseError> {
+    let monkeys: HashMap<_, _> = parse(input).collect::<Result<_, _>>()?;
     let mut values = HashMap::new();
     for name in topsort(&monkeys) {
         let value = match &monkeys[name] {
@@ -183,11 +189,11 @@ pub(crate) fn solve(input: &str) -> isize {
         };
         values.insert(name, value);
     }
-    values["root"]
+    Ok(values["root"])
 }
 
-fn get_expression(input: &str) -> Rc<Expr> {
-    let monkeys: HashMap<_, _> = parse(input).collect();
+fn get_expression(input: &str) -> Result<Rc<Expr>, ParseError> {
+    let monkeys: HashMap<_, _> = parse(input).collect::<Result<_, _>>()?;
     let mut expressions: HashMap<&str, Rc<Expr>> = HashMap::new();
     for name in topsort(&monkeys) {
         let expr = match (name, &monkeys[name]) {
@@ -210,13 +216,26 @@ fn get_expression(input: &str) -> Rc<Expr> {
         };
         expressions.insert(name, Rc::new(expr));
     }
-    Rc::clone(&expressions["root"])
+    Ok(Rc::clone(&expressions["root"]))
+}
+
+// Replaces every subtree without an unknown by its value, bottom-up
+fn fold_constants(expr: &Rc<Expr>) -> Rc<Expr> {
+    let Expr::BinaryOperation(lhs, rhs, op) = expr.as_ref() else {
+        return Rc::clone(expr);
+    };
+    let (folded_lhs, folded_rhs) = (fold_constants(lhs), fold_constants(rhs));
+    match (folded_lhs.as_ref(), folded_rhs.as_ref()) {
+        (Expr::Literal(lhs), Expr::Literal(rhs)) => Rc::new(Expr::Literal(op.eval(*lhs, *rhs))),
+        _ if Rc::ptr_eq(lhs, &folded_lhs) && Rc::ptr_eq(rhs, &folded_rhs) => Rc::clone(expr),
+        _ => Rc::new(Expr::BinaryOperation(folded_lhs, folded_rhs, *op)),
+    }
 }
 
-pub(crate) fn solve_2(input: &str) -> isize {
-    let expr = get_expression(input);
+pub(crate) fn solve_2(input: &str) -> Result<isize, ParseError> {
+    let expr = fold_constants(&get_expression(input)?);
     println!("{expr}");
-    expr.simplify().find_unknown(0)
+    Ok(expr.simplify().find_unknown(0))
 }
 
 #[cfg(test)]
@@ -246,7 +265,7 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let ops = parse(EXAMPLE).take(3).collect_vec();
+        let ops = parse(EXAMPLE).take(3).map(Result::unwrap).collect_vec();
         assert_eq!(
             ops,
             vec![
@@ -263,13 +282,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_malformed() {
+        let input = "root: humn % abcd\nhumn: 5\nabcd: 3\n";
+        assert_eq!(
+            parse_count(input),
+            Err(ParseError::new("root: humn % abcd", EXPECTED))
+        );
+        assert_eq!(
+            solve("root: humn\n").unwrap_err(),
+            ParseError::new("root: humn", EXPECTED)
+        );
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let literal = |value| Rc::new(Expr::Literal(value));
+        let op = |lhs, rhs, op| Rc::new(Expr::BinaryOperation(lhs, rhs, op));
+        // ((2 + 3) * (4 - 1)) / 5
+        let constant = op(
+            op(
+                op(literal(2), literal(3), Op::Add),
+                op(literal(4), literal(1), Op::Sub),
+                Op::Mul,
+            ),
+            literal(5),
+            Op::Div,
+        );
+        assert!(matches!(
+            fold_constants(&constant).as_ref(),
+            Expr::Literal(3)
+        ));
+
+        let unknown = op(Rc::new(Expr::Unknown), Rc::clone(&constant), Op::Sub);
+        assert_eq!(fold_constants(&unknown).to_string(), "(x - 3)");
+        let folded = fold_constants(&unknown);
+        assert!(Rc::ptr_eq(&fold_constants(&folded), &folded));
+        // The example's root already folds everything but the path to humn
+        let expr = get_expression(EXAMPLE).unwrap();
+        assert_eq!(fold_constants(&expr).to_string(), expr.to_string());
+        assert_eq!(expr.to_string(), "(((4 + (2 * (x - 3))) / 4) - 150)");
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(solve(EXAMPLE), 152);
+        assert_eq!(solve(EXAMPLE), Ok(152));
     }
 
     #[test]
     fn test_solve_2() {
-        assert_eq!(solve_2(EXAMPLE), 301);
+        assert_eq!(solve_2(EXAMPLE), Ok(301));
     }
 }
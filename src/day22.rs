@@ -1,8 +1,10 @@
-use std::{collections::HashMap, iter::zip};
+use std::{collections::HashMap, iter::zip, str::FromStr};
 
 use itertools::Itertools;
 
-#[derive(Clone, Copy)]
+use crate::utils::Direction;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Cell {
     Wall,
     Open,
@@ -78,6 +80,10 @@ impl Board {
         }
     }
 
+    fn cell(&self, x: isize, y: isize) -> Option<Cell> {
+        self.cells.get(&(x, y)).copied()
+    }
+
     fn add_discontinuity(&mut self, scale: isize, a: Line, b: Line) {
         let mut add_directional_discontinuity = |from: Line, to: Line| {
             let src_facing = from.src_facing();
@@ -118,12 +124,26 @@ impl Board {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum Facing {
-    Up,
-    Down,
-    Left,
-    Right,
+// The board's rows, without any leading blank lines or the instructions
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_rows(s.lines().skip_while(|l| l.is_empty())).map(Board::new)
+    }
+}
+
+type Facing = Direction;
+
+impl Facing {
+    fn score(self) -> isize {
+        match self {
+            Facing::Right => 0,
+            Facing::Down => 1,
+            Facing::Left => 2,
+            Facing::Up => 3,
+        }
+    }
 }
 
 struct PlayerWalker<'a> {
@@ -141,25 +161,17 @@ impl<'a> Iterator for PlayerWalker<'a> {
         let new_player = match self.board.discontinuities.get(&player) {
             Some(&new_player) => new_player,
             None => {
-                let x = player.x;
-                let y = player.y;
-                let (new_x, new_y) = match player.facing {
-                    Facing::Right => (x + 1, y),
-                    Facing::Left => (x - 1, y),
-                    Facing::Up => (x, y - 1),
-                    Facing::Down => (x, y + 1),
-                };
+                let (dx, dy) = player.facing.to_vector();
                 Player {
-                    x: new_x,
-                    y: new_y,
+                    x: player.x + dx,
+                    y: player.y + dy,
                     facing: player.facing,
                 }
             }
         };
         if let Cell::Open = self
             .board
-            .cells
-            .get(&(new_player.x, new_player.y))
+            .cell(new_player.x, new_player.y)
             .expect(&format!("OOB (x: {}, y: {})", new_player.x, new_player.y))
         {
             self.player = Some(new_player);
@@ -178,55 +190,51 @@ struct Player {
 impl Player {
     fn turn_left(self) -> Self {
         Self {
-            facing: match self.facing {
-                Facing::Down => Facing::Right,
-                Facing::Right => Facing::Up,
-                Facing::Up => Facing::Left,
-                Facing::Left => Facing::Down,
-            },
+            facing: self.facing.ccw(),
             ..self
         }
     }
 
     fn turn_right(self) -> Self {
         Self {
-            facing: match self.facing {
-                Facing::Right => Facing::Down,
-                Facing::Up => Facing::Right,
-                Facing::Left => Facing::Up,
-                Facing::Down => Facing::Left,
-            },
+            facing: self.facing.cw(),
             ..self
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 enum Instruction {
     Forward(usize),
     Left,
     Right,
 }
 
+fn parse_rows<'a>(
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<Vec<Vec<Option<Cell>>>, String> {
+    lines
+        .map(|l| {
+            l.chars()
+                .map(|c| match c {
+                    ' ' => Ok(None),
+                    '.' => Ok(Some(Cell::Open)),
+                    '#' => Ok(Some(Cell::Wall)),
+                    _ => Err(format!("Unexpected character in board: {c}")),
+                })
+                .collect()
+        })
+        .collect()
+}
+
 fn parse(input: &str) -> (Board, Vec<Instruction>) {
     let mut lines = input.lines().skip_while(|l| l.is_empty());
 
-    let board = Board::new(
-        lines
-            .take_while_ref(|l| !l.is_empty())
-            .map(|l| {
-                l.chars()
-                    .map(|c| match c {
-                        ' ' => None,
-                        '.' => Some(Cell::Open),
-                        '#' => Some(Cell::Wall),
-                        _ => panic!("Unexpected character in board: {c}"),
-                    })
-                    .collect_vec()
-            })
-            .collect(),
-    );
-
-    let mut chars = lines.skip(1).next().unwrap().trim().chars().peekable();
+    let board = Board::new(parse_rows(lines.take_while_ref(|l| !l.is_empty())).unwrap());
+
+    // The path may have been wrapped across several lines
+    let path: String = lines.map(str::trim).collect();
+    let mut chars = path.chars().peekable();
     let mut instructions = Vec::new();
     while let Some(c) = chars.next() {
         if let Some(mut num) = c.to_digit(10) {
@@ -246,7 +254,26 @@ fn parse(input: &str) -> (Board, Vec<Instruction>) {
     (board, instructions)
 }
 
-fn compute(board: Board, instructions: Vec<Instruction>) -> isize {
+// The path as it appears in the input, so parsing can be round-tripped
+fn format_instructions(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::Forward(n) => n.to_string(),
+            Instruction::Left => "L".to_string(),
+            Instruction::Right => "R".to_string(),
+        })
+        .collect()
+}
+
+// The path as parsed, to compare against the input
+pub(crate) fn report(input: &str) -> String {
+    let (_, instructions) = parse(input);
+    let path = format_instructions(&instructions);
+    format!("{} instructions: {path}", instructions.len())
+}
+
+fn final_state(board: &Board, instructions: Vec<Instruction>) -> Player {
     let mut player = board.initial_player;
     for instruction in instructions {
         player = match instruction {
@@ -255,14 +282,12 @@ fn compute(board: Board, instructions: Vec<Instruction>) -> isize {
             Instruction::Forward(distance) => board.walk(player).take(distance + 1).last().unwrap(),
         }
     }
-    1000 * (player.y + 1)
-        + 4 * (player.x + 1)
-        + match player.facing {
-            Facing::Right => 0,
-            Facing::Down => 1,
-            Facing::Left => 2,
-            Facing::Up => 3,
-        }
+    player
+}
+
+fn compute(board: Board, instructions: Vec<Instruction>) -> isize {
+    let player = final_state(&board, instructions);
+    1000 * (player.y + 1) + 4 * (player.x + 1) + player.facing.score()
 }
 
 pub(crate) fn solve(input: &str) -> isize {
@@ -326,7 +351,7 @@ mod tests {
 
     use super::*;
 
-    const _EXAMPLE: &str = "
+    const EXAMPLE: &str = "
         ...#
         .#..
         #...
@@ -343,6 +368,92 @@ mod tests {
         10R5L5R10L4R5L5
     ";
 
+    #[test]
+    fn test_format_instructions() {
+        let (_, instructions) = parse(EXAMPLE);
+        assert_eq!(format_instructions(&instructions), "10R5L5R10L4R5L5");
+        assert_eq!(format_instructions(&[]), "");
+        assert_eq!(report(EXAMPLE), "13 instructions: 10R5L5R10L4R5L5");
+    }
+
+    #[test]
+    fn test_wrapped_instructions() {
+        let (_, instructions) = parse(EXAMPLE);
+        let wrapped = EXAMPLE.replace("10R5L5R10", "10R5L5R1\n        0");
+        let (_, wrapped_instructions) = parse(&wrapped);
+        assert_eq!(instructions.len(), 13);
+        assert_eq!(wrapped_instructions, instructions);
+    }
+
+    #[test]
+    fn test_final_state() {
+        //       0  1  2  3
+        //    0        o
+        //    1  o  o  o
+        //    2        o  o
+        let (mut board, instructions) = parse(EXAMPLE);
+        board.add_discontinuity(4, Line::Left(2, 0), Line::Right(3, 0));
+        board.add_discontinuity(4, Line::Left(0, 1), Line::Right(3, 1));
+        board.add_discontinuity(4, Line::Left(2, 2), Line::Right(4, 2));
+        board.add_discontinuity(4, Line::Top(0, 1), Line::Bottom(0, 2));
+        board.add_discontinuity(4, Line::Top(1, 1), Line::Bottom(1, 2));
+        board.add_discontinuity(4, Line::Top(2, 0), Line::Bottom(2, 3));
+        board.add_discontinuity(4, Line::Top(3, 2), Line::Bottom(3, 3));
+        // Row 6, column 8, facing right
+        let player = final_state(&board, instructions);
+        assert_eq!((player.y + 1, player.x + 1), (6, 8));
+        assert_eq!(player.facing, Facing::Right);
+        assert_eq!(player.facing.score(), 0);
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut player = Player {
+            x: 0,
+            y: 0,
+            facing: Facing::Right,
+        };
+        let mut scores = Vec::new();
+        for _ in 0..4 {
+            player = player.turn_right();
+            scores.push(player.facing.score());
+        }
+        assert_eq!(scores, vec![1, 2, 3, 0]);
+        let mut scores = Vec::new();
+        for _ in 0..4 {
+            player = player.turn_left();
+            scores.push(player.facing.score());
+        }
+        assert_eq!(scores, vec![3, 2, 1, 0]);
+        assert_eq!(player.turn_left().facing, Facing::Up);
+        assert_eq!(player.turn_left().turn_right(), player);
+    }
+
+    #[test]
+    fn test_board_from_str() {
+        // Two 2x2 faces side by side, wrapped into a loop
+        let mut board = Board::from_str("\n..#.\n....\n").unwrap();
+        assert_eq!(board.cell(0, 0), Some(Cell::Open));
+        assert_eq!(board.cell(2, 0), Some(Cell::Wall));
+        assert_eq!(board.cell(4, 0), None);
+        assert_eq!(board.cell(0, 2), None);
+        let start = board.initial_player;
+        assert_eq!((start.x, start.y, start.facing), (0, 0, Facing::Right));
+
+        board.add_discontinuity(2, Line::Right(2, 0), Line::Left(0, 0));
+        let player = Player {
+            x: 2,
+            y: 1,
+            facing: Facing::Right,
+        };
+        let end = board.walk(player).take(3).last().unwrap();
+        assert_eq!((end.x, end.y, end.facing), (0, 1, Facing::Right));
+        let back = board.walk(end.turn_left().turn_left()).take(2).last().unwrap();
+        assert_eq!((back.x, back.y, back.facing), (3, 1, Facing::Left));
+
+        assert!(Board::from_str("..x.").is_err());
+    }
+
     #[test]
     fn test_line_bottom() {
         let players = Line::Bottom(2, 2).to_coords(5).collect_vec();
@@ -5,6 +5,8 @@ use std::{
 
 use itertools::Itertools;
 
+use crate::utils::{Vec2, Visualize};
+
 struct BBox {
     top_left: Point,
     bottom_right: Point,
@@ -15,10 +17,10 @@ impl BBox {
         let mut top_left = points.next().unwrap();
         let mut bottom_right = top_left;
         for point in points {
-            top_left.0[0] = top_left.0[0].min(point.0[0]);
-            top_left.0[1] = top_left.0[1].min(point.0[1]);
-            bottom_right.0[0] = bottom_right.0[0].max(point.0[0]);
-            bottom_right.0[1] = bottom_right.0[1].max(point.0[1]);
+            top_left.x = top_left.x.min(point.x);
+            top_left.y = top_left.y.min(point.y);
+            bottom_right.x = bottom_right.x.max(point.x);
+            bottom_right.y = bottom_right.y.max(point.y);
         }
         Self {
             top_left,
@@ -27,55 +29,42 @@ impl BBox {
     }
 
     fn width(&self) -> usize {
-        (self.bottom_right.0[0] - self.top_left.0[0] + 1) as usize
+        (self.bottom_right.x - self.top_left.x + 1) as usize
     }
 
     fn height(&self) -> usize {
-        (self.bottom_right.0[1] - self.top_left.0[1] + 1) as usize
+        (self.bottom_right.y - self.top_left.y + 1) as usize
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-struct Point([isize; 2]);
-
-impl Point {
-    fn apply_vector(mut self, vector: [isize; 2]) -> Self {
-        self.0[0] += vector[0];
-        self.0[1] += vector[1];
-        self
-    }
+type Point = Vec2<isize>;
 
-    fn apply_direction(mut self, direction: Direction) -> Self {
-        self.0[direction.dim_index] += direction.vector;
-        self
-    }
-}
-
-fn adjacent_vectors() -> impl Iterator<Item = [isize; 2]> {
+fn adjacent_vectors() -> impl Iterator<Item = Point> {
     (0..2)
         .map(|_| [-1, 0, 1].into_iter())
         .multi_cartesian_product()
-        .map(|v| v.try_into().unwrap())
-        .filter(|&p| p != [0, 0])
+        .map(|v| Point::new(v[0], v[1]))
+        .filter(|&p| p != Point::new(0, 0))
 }
 
-#[derive(Clone, Copy)]
-struct Direction {
-    dim_index: usize,
-    vector: isize,
+// The three adjacent points lying in the given (unit, axis-aligned) direction
+fn points_towards(direction: Point, point: Point) -> impl Iterator<Item = Point> {
+    adjacent_vectors()
+        .filter(move |&v| v.dot(direction) == 1)
+        .map(move |v| point + v)
 }
 
-impl Direction {
-    fn points(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
-        adjacent_vectors()
-            .filter(|p| p[self.dim_index] == self.vector)
-            .map(move |p| point.apply_vector(p))
-    }
-}
+// North, south, west, east
+const DIRECTIONS: [Point; 4] = [
+    Point::new(0, -1),
+    Point::new(0, 1),
+    Point::new(-1, 0),
+    Point::new(1, 0),
+];
 
 struct Elves {
     positions: HashSet<Point>,
-    directions: Vec<Direction>,
+    directions: Vec<Point>,
 }
 
 impl Display for Elves {
@@ -83,7 +72,7 @@ impl Display for Elves {
         let bounds = self.bounds();
         for row in 0..bounds.height() as isize {
             for col in 0..bounds.width() as isize {
-                let point = bounds.top_left.apply_vector([col, row]);
+                let point = bounds.top_left + Point::new(col, row);
                 f.write_char(match self.positions.contains(&point) {
                     true => '#',
                     false => '.',
@@ -95,10 +84,16 @@ impl Display for Elves {
     }
 }
 
+impl Visualize for Elves {
+    fn visualize(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl Elves {
     fn new(input: &str) -> Self {
-        Self {
-            positions: input
+        Self::from_points(
+            input
                 .lines()
                 .map(|l| l.trim())
                 .filter(|l| !l.is_empty())
@@ -106,36 +101,54 @@ impl Elves {
                 .flat_map(|(y, l)| {
                     l.char_indices()
                         .filter(|&(_, c)| c == '#')
-                        .map(move |(x, _)| Point([x as isize, y as isize]))
+                        .map(move |(x, _)| Point::new(x as isize, y as isize))
                 })
                 .collect(),
-            directions: vec![
-                Direction {
-                    dim_index: 1,
-                    vector: -1,
-                },
-                Direction {
-                    dim_index: 1,
-                    vector: 1,
-                },
-                Direction {
-                    dim_index: 0,
-                    vector: -1,
-                },
-                Direction {
-                    dim_index: 0,
-                    vector: 1,
-                },
-            ],
+        )
+    }
+
+    fn from_points(positions: HashSet<Point>) -> Self {
+        Self {
+            positions,
+            directions: DIRECTIONS.to_vec(),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_directions(self, directions: [Point; 4]) -> Self {
+        Self {
+            directions: directions.to_vec(),
+            ..self
         }
     }
 
     fn round(&mut self) -> bool {
+        self.round_debug().0
+    }
+
+    // Like `round`, but also returns the destinations more than one elf proposed
+    fn round_debug(&mut self) -> (bool, HashSet<Point>) {
+        let count = self.positions.len();
+        let (good_proposals, contested) = self.proposals();
+
+        // Move only the points that moved; destinations were empty, so removing
+        // every source before inserting can't lose an elf
+        for point in good_proposals.keys() {
+            self.positions.remove(point);
+        }
+        self.positions.extend(good_proposals.values());
+        debug_assert_eq!(self.positions.len(), count, "elves were created or lost");
+        (!good_proposals.is_empty(), contested)
+    }
+
+    // The uncontested moves for this round, and the contested destinations.
+    // Rotates the direction order, but leaves the elves where they are.
+    fn proposals(&mut self) -> (HashMap<Point, Point>, HashSet<Point>) {
         // Which points have at least one other point adjacent to them?
         let mut will_propose = HashSet::new();
         for &point in &self.positions {
             if adjacent_vectors()
-                .map(|v| point.apply_vector(v))
+                .map(|v| point + v)
                 .any(|p| self.positions.contains(&p))
             {
                 will_propose.insert(point);
@@ -147,10 +160,10 @@ impl Elves {
         let mut destination_counts: HashMap<Point, usize> = HashMap::new();
         for point in will_propose {
             for &direction in &self.directions {
-                if direction.points(point).any(|p| self.positions.contains(&p)) {
+                if points_towards(direction, point).any(|p| self.positions.contains(&p)) {
                     continue;
                 }
-                let new_point = point.apply_direction(direction);
+                let new_point = point + direction;
                 proposals.insert(point, new_point);
                 *destination_counts.entry(new_point).or_default() += 1;
                 break;
@@ -165,19 +178,28 @@ impl Elves {
                 good_proposals.insert(point, new_point);
             }
         }
-
-        // Move the points to their new positions
-        let mut new_positions = HashSet::new();
-        for &point in &self.positions {
-            new_positions.insert(*good_proposals.get(&point).unwrap_or(&point));
-        }
-        self.positions = new_positions;
-        !good_proposals.is_empty()
+        let contested = destination_counts
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(point, _)| point)
+            .collect();
+        (good_proposals, contested)
     }
 
     fn bounds(&self) -> BBox {
         BBox::new(self.positions.iter().copied())
     }
+
+    fn empty_tiles(&self) -> usize {
+        let bounds = self.bounds();
+        bounds.width() * bounds.height() - self.positions.len()
+    }
+}
+
+pub(crate) fn visualize(input: &str) -> String {
+    let mut elves = Elves::new(input);
+    while elves.round() {}
+    elves.visualize()
 }
 
 pub(crate) fn solve(input: &str) -> usize {
@@ -185,20 +207,33 @@ pub(crate) fn solve(input: &str) -> usize {
     for _ in 0..10 {
         elves.round();
     }
-    let bounds = elves.bounds();
-    bounds.width() * bounds.height() - elves.positions.len()
+    elves.empty_tiles()
 }
 
-pub(crate) fn solve_2(input: &str) -> usize {
+// (empty tiles after 10 rounds, first round where no elf moves) from one simulation
+fn solve_both(input: &str) -> (usize, usize) {
     let mut elves = Elves::new(input);
+    let (mut empty_tiles, mut stable_round) = (None, None);
     for i in 1.. {
+        // Keep going past an early stop, since part 1 always wants 10 rounds
         if !elves.round() {
-            return i;
+            stable_round.get_or_insert(i);
+        }
+        if i == 10 {
+            empty_tiles = Some(elves.empty_tiles());
+        }
+        if let (Some(empty_tiles), Some(stable_round)) = (empty_tiles, stable_round) {
+            return (empty_tiles, stable_round);
         }
     }
     panic!()
 }
 
+// Counting part 1's empty tiles along the way costs next to nothing
+pub(crate) fn solve_2(input: &str) -> usize {
+    solve_both(input).1
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -223,12 +258,12 @@ mod tests {
         ",
         );
         assert_eq!(elves.positions.len(), 4);
-        assert!(elves.positions.contains(&Point([1, 0])));
-        assert!(elves.positions.contains(&Point([3, 0])));
-        assert!(elves.positions.contains(&Point([2, 1])));
-        assert!(elves.positions.contains(&Point([0, 1])));
-        assert_eq!(&elves.bounds().top_left, &Point([0, 0]));
-        assert_eq!(&elves.bounds().bottom_right, &Point([3, 1]));
+        assert!(elves.positions.contains(&Point::new(1, 0)));
+        assert!(elves.positions.contains(&Point::new(3, 0)));
+        assert!(elves.positions.contains(&Point::new(2, 1)));
+        assert!(elves.positions.contains(&Point::new(0, 1)));
+        assert_eq!(&elves.bounds().top_left, &Point::new(0, 0));
+        assert_eq!(&elves.bounds().bottom_right, &Point::new(3, 1));
         assert_eq!(elves.bounds().width(), 4);
         assert_eq!(elves.bounds().height(), 2);
     }
@@ -240,19 +275,103 @@ mod tests {
         assert_eq!(
             vecs,
             vec![
-                [-1, -1],
-                [-1, 0],
-                [-1, 1],
-                [0, -1],
-                // [0, 0],
-                [0, 1],
-                [1, -1],
-                [1, 0],
-                [1, 1]
+                Point::new(-1, -1),
+                Point::new(-1, 0),
+                Point::new(-1, 1),
+                Point::new(0, -1),
+                // Point::new(0, 0),
+                Point::new(0, 1),
+                Point::new(1, -1),
+                Point::new(1, 0),
+                Point::new(1, 1)
             ]
         )
     }
 
+    #[test]
+    fn test_small_example() {
+        let points = |points: &[(isize, isize)]| {
+            points
+                .iter()
+                .map(|&(x, y)| Point::new(x, y))
+                .collect::<HashSet<_>>()
+        };
+        let mut elves = Elves::from_points(points(&[(2, 1), (3, 1), (2, 2), (2, 4), (3, 4)]));
+        assert!(elves.round());
+        assert_eq!(
+            elves.positions,
+            points(&[(2, 0), (3, 0), (2, 2), (3, 3), (2, 4)])
+        );
+        assert!(elves.round());
+        assert!(elves.round());
+        assert_eq!(
+            elves.positions,
+            points(&[(2, 0), (4, 1), (0, 2), (4, 3), (2, 5)])
+        );
+        assert!(!elves.round());
+    }
+
+    #[test]
+    fn test_direction_order() {
+        let pair = [Point::new(0, 0), Point::new(0, 1)];
+        let mut elves = Elves::from_points(pair.into());
+        elves.round();
+        assert_eq!(elves.positions, [Point::new(0, -1), Point::new(0, 2)].into());
+
+        let west_first = [DIRECTIONS[2], DIRECTIONS[0], DIRECTIONS[1], DIRECTIONS[3]];
+        let mut elves = Elves::from_points(pair.into()).with_directions(west_first);
+        elves.round();
+        assert_eq!(elves.positions, [Point::new(-1, 0), Point::new(-1, 1)].into());
+    }
+
+    #[test]
+    fn test_round_debug() {
+        // The middle pair both propose (0, 1): one is blocked to the north by
+        // the elf above it, the other has its neighbour to the south-east
+        let elves = [(0, -1), (0, 0), (0, 2), (1, 3)].map(|(x, y)| Point::new(x, y));
+        let mut elves = Elves::from_points(elves.into());
+        let (moved, contested) = elves.round_debug();
+        assert!(moved);
+        assert_eq!(contested, [Point::new(0, 1)].into());
+        assert!(elves.positions.contains(&Point::new(0, 0)));
+        assert!(elves.positions.contains(&Point::new(0, 2)));
+        assert!(!elves.positions.contains(&Point::new(0, 1)));
+    }
+
+    #[test]
+    fn test_large_grid() {
+        let positions: HashSet<_> = (0..50)
+            .cartesian_product(0..50)
+            .filter(|&(x, y)| (x * 7 + y * 13) % 5 < 2)
+            .map(|(x, y)| Point::new(x, y))
+            .collect();
+        let count = positions.len();
+        let mut elves = Elves::from_points(positions.clone());
+        let mut rebuilt = Elves::from_points(positions);
+        for _ in 0..100 {
+            assert!(elves.round());
+            // Moving elves in place must match rebuilding the whole set
+            let (good_proposals, _) = rebuilt.proposals();
+            rebuilt.positions = rebuilt
+                .positions
+                .iter()
+                .map(|point| *good_proposals.get(point).unwrap_or(point))
+                .collect();
+            assert_eq!(elves.positions, rebuilt.positions);
+        }
+        // A collision would have merged two elves in the set
+        assert_eq!(elves.positions.len(), count);
+    }
+
+    #[test]
+    fn test_elf_count() {
+        let mut elves = Elves::new(EXAMPLE);
+        for _ in 0..30 {
+            elves.round();
+            assert_eq!(elves.positions.len(), 22);
+        }
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(solve(EXAMPLE), 110);
@@ -262,4 +381,20 @@ mod tests {
     fn test_solve_2() {
         assert_eq!(solve_2(EXAMPLE), 20);
     }
+
+    #[test]
+    fn test_solve_both() {
+        assert_eq!(solve_both(EXAMPLE), (110, 20));
+        // These elves settle after three moves, well before round 10
+        let small = "
+            .....
+            ..##.
+            ..#..
+            .....
+            ..##.
+            .....
+        ";
+        assert_eq!(solve_both(small), (solve(small), solve_2(small)));
+        assert_eq!(solve_2(small), 4);
+    }
 }
@@ -1,24 +1,26 @@
-use std::{
-    collections::{BinaryHeap, HashSet},
-    hash::Hash,
-};
-
 use itertools::Itertools;
 
+use crate::utils::astar;
+#[cfg(test)]
+use crate::utils::dijkstra;
+
+// Blizzards along one row or column that all move `speed` cells per minute
 #[derive(Clone, Copy)]
 struct WindTracker {
     l_bits: u128,
     r_bits: u128,
     length: usize,
+    speed: usize,
 }
 
 impl WindTracker {
-    fn new(length: usize) -> Self {
+    fn new(length: usize, speed: usize) -> Self {
         assert!(length < 128);
         Self {
             l_bits: 0,
             r_bits: 0,
             length,
+            speed,
         }
     }
 
@@ -30,18 +32,53 @@ impl WindTracker {
         self.r_bits |= 1 << pos;
     }
 
+    fn rightward_at(&self, time: usize) -> u128 {
+        let time = time * self.speed % self.length;
+        let m0: u128 = (1 << (self.length - time)) - 1;
+        let m1: u128 = (1 << time) - 1;
+        ((self.l_bits & m0) << time) | ((self.l_bits >> (self.length - time)) & m1)
+    }
+
+    fn leftward_at(&self, time: usize) -> u128 {
+        let time = time * self.speed % self.length;
+        let m0: u128 = (1 << (self.length - time)) - 1;
+        let m1: u128 = (1 << time) - 1;
+        ((self.r_bits >> time) & m0) | ((self.r_bits & m1) << (self.length - time))
+    }
+
+    fn blocked_at(&self, time: usize) -> u128 {
+        self.rightward_at(time) | self.leftward_at(time)
+    }
+
+    #[cfg(test)]
     fn is_clear(&self, time: usize, pos: usize) -> bool {
-        let time = time % self.length;
-        let m0 = (1 << (self.length - time)) - 1;
-        let m1 = (1 << time) - 1;
-        let bits = ((self.l_bits & m0) << time)
-            | ((self.l_bits >> (self.length - time)) & m1)
-            | ((self.r_bits >> time) & m0)
-            | ((self.r_bits & m1) << (self.length - time));
-        bits & (1 << pos) == 0
+        self.blocked_at(time) & (1 << pos) == 0
     }
 }
 
+// The tracker in a row or column for blizzards of the given speed
+fn tracker_for(trackers: &mut Vec<WindTracker>, length: usize, speed: usize) -> &mut WindTracker {
+    match trackers.iter().position(|tracker| tracker.speed == speed) {
+        Some(i) => &mut trackers[i],
+        None => {
+            trackers.push(WindTracker::new(length, speed));
+            trackers.last_mut().unwrap()
+        }
+    }
+}
+
+// Blocked bits of a row or column for every time modulo its length, since the
+// winds repeat
+fn blocked_table(trackers: &[WindTracker], length: usize) -> Vec<u128> {
+    (0..length)
+        .map(|time| {
+            trackers
+                .iter()
+                .fold(0, |bits, tracker| bits | tracker.blocked_at(time))
+        })
+        .collect()
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct State {
     time: usize,
@@ -49,14 +86,37 @@ struct State {
 }
 
 struct Board {
-    ver_winds: Vec<WindTracker>,
-    hor_winds: Vec<WindTracker>,
+    // One tracker per blizzard speed in each column and row
+    ver_winds: Vec<Vec<WindTracker>>,
+    hor_winds: Vec<Vec<WindTracker>>,
+    // `blocked_table` of each column and row, so the search never rotates bits
+    ver_blocked: Vec<Vec<u128>>,
+    hor_blocked: Vec<Vec<u128>>,
     start_pos: (i8, i8),
     end_pos: (i8, i8),
 }
 
 impl Board {
+    // Starts and ends at the gaps in the top and bottom walls
     fn new(input: &str) -> Self {
+        let rows = input
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect_vec();
+        // Less one for the left wall, as the endpoints are in interior coordinates
+        let gap = |row: &str| row.find('.').expect("No gap in the wall") as i8 - 1;
+        let height = rows.len() as i8 - 2;
+        Self::with_endpoints(
+            input,
+            (gap(rows[0]), -1),
+            (gap(rows[rows.len() - 1]), height),
+        )
+    }
+
+    // Like `new`, with the blizzard starting at interior `(x, y)` moving
+    // `speed(x, y)` cells per minute, and the gaps in the corners
+    fn with_speeds(input: &str, speed: impl Fn(usize, usize) -> usize) -> Self {
         let mut lines = input
             .lines()
             .map(|l| l.trim())
@@ -68,16 +128,19 @@ impl Board {
 
         let width = lines[0].len();
         let height = lines.len();
-        let mut ver_winds = vec![WindTracker::new(height); width];
-        let mut hor_winds = vec![WindTracker::new(width); height];
+        let mut ver_winds = vec![Vec::new(); width];
+        let mut hor_winds = vec![Vec::new(); height];
         for (y, row) in lines.iter().enumerate() {
             for (x, c) in row.iter().enumerate() {
+                let (trackers, length, pos) = match c {
+                    '^' | 'v' => (&mut ver_winds[x], height, y),
+                    '<' | '>' => (&mut hor_winds[y], width, x),
+                    _ => continue,
+                };
+                let tracker = tracker_for(trackers, length, speed(x, y));
                 match c {
-                    '^' => ver_winds[x].set_leftward(y),
-                    'v' => ver_winds[x].set_rightward(y),
-                    '<' => hor_winds[y].set_leftward(x),
-                    '>' => hor_winds[y].set_rightward(x),
-                    _ => (),
+                    '^' | '<' => tracker.set_leftward(pos),
+                    _ => tracker.set_rightward(pos),
                 }
             }
         }
@@ -85,11 +148,28 @@ impl Board {
         Self {
             start_pos: (0, -1),
             end_pos: (width as i8 - 1, height as i8),
+            ver_blocked: ver_winds.iter().map(|c| blocked_table(c, height)).collect(),
+            hor_blocked: hor_winds.iter().map(|r| blocked_table(r, width)).collect(),
             ver_winds,
             hor_winds,
         }
     }
 
+    // Endpoints are in interior coordinates, so they normally lie just outside it
+    fn with_endpoints(input: &str, start: (i8, i8), end: (i8, i8)) -> Self {
+        Self {
+            start_pos: start,
+            end_pos: end,
+            ..Self::with_speeds(input, |_, _| 1)
+        }
+    }
+
+    fn is_clear(&self, time: usize, x: usize, y: usize) -> bool {
+        let hor = &self.hor_blocked[y];
+        let ver = &self.ver_blocked[x];
+        ((hor[time % hor.len()] >> x) | (ver[time % ver.len()] >> y)) & 1 == 0
+    }
+
     fn next_states(&self, state: State) -> impl Iterator<Item = State> + '_ {
         let width = self.ver_winds.len() as i8;
         let height = self.hor_winds.len() as i8;
@@ -101,7 +181,7 @@ impl Board {
                 return false;
             }
             let (x, y) = (x as usize, y as usize);
-            self.hor_winds[y].is_clear(time, x) && self.ver_winds[x].is_clear(time, y)
+            self.is_clear(time, x, y)
         };
         [(-1, 0), (1, 0), (0, -1), (0, 1), (0, 0)]
             .into_iter()
@@ -113,41 +193,99 @@ impl Board {
     }
 
     fn fastest_path(&self, pos: (i8, i8), end: (i8, i8), time: usize) -> usize {
-        let wrap_cost = |s: State| {
-            let dist_to_goal = s.pos.0.abs_diff(end.0) + s.pos.1.abs_diff(end.1);
-            let best_case_cost = s.time as isize + dist_to_goal as isize;
-            (-best_case_cost, s)
-        };
-        let mut queue: BinaryHeap<_> = [wrap_cost(State { time, pos })].into();
-        let mut seen = HashSet::new();
-        while let Some((_, state)) = queue.pop() {
-            if !seen.insert(state) {
-                continue;
-            }
-            if state.pos == end {
-                return state.time;
+        let heuristic =
+            |s: &State| s.pos.0.abs_diff(end.0) as usize + s.pos.1.abs_diff(end.1) as usize;
+        let (cost, _) = astar(
+            State { time, pos },
+            |&s| self.next_states(s).map(|s| (s, 1)),
+            heuristic,
+            |s| s.pos == end,
+        )
+        .unwrap();
+        time + cost
+    }
+
+    #[cfg(test)]
+    fn fastest_path_dijkstra(&self, pos: (i8, i8), end: (i8, i8), time: usize) -> usize {
+        let (cost, _) = dijkstra(
+            State { time, pos },
+            |&s| self.next_states(s).map(|s| (s, 1)),
+            |s| s.pos == end,
+        )
+        .unwrap();
+        time + cost
+    }
+
+    fn render_at(&self, time: usize) -> String {
+        let mut result = String::new();
+        for (y, hor_winds) in self.hor_winds.iter().enumerate() {
+            for (x, ver_winds) in self.ver_winds.iter().enumerate() {
+                let ver = ver_winds.iter().flat_map(|wind| {
+                    let (up, down) = (wind.leftward_at(time), wind.rightward_at(time));
+                    [(up, y, '^'), (down, y, 'v')]
+                });
+                let hor = hor_winds.iter().flat_map(|wind| {
+                    let (left, right) = (wind.leftward_at(time), wind.rightward_at(time));
+                    [(left, x, '<'), (right, x, '>')]
+                });
+                let winds = ver
+                    .chain(hor)
+                    .filter(|&(bits, pos, _)| bits & (1 << pos) != 0)
+                    .map(|(_, _, c)| c)
+                    .collect_vec();
+                result.push(match winds[..] {
+                    [] => '.',
+                    [c] => c,
+                    _ => char::from_digit(winds.len() as u32, 10).unwrap(),
+                });
             }
-            queue.extend(self.next_states(state).map(wrap_cost));
+            result.push('\n');
         }
-        panic!();
+        result
     }
 
     fn solve<const LENGTHS: usize>(&self) -> usize {
+        self.solve_trips(LENGTHS)
+    }
+
+    // The time each leg arrives, searching each trip in turn from the last arrival
+    fn trip_times(&self, trips: usize) -> Vec<usize> {
         [self.start_pos, self.end_pos]
             .into_iter()
             .cycle()
-            .take(LENGTHS + 1)
+            .take(trips + 1)
             .tuple_windows()
-            .fold(0, |time, (pos, end)| self.fastest_path(pos, end, time))
+            .scan(0, |time, (pos, end)| {
+                *time = self.fastest_path(pos, end, *time);
+                Some(*time)
+            })
+            .collect()
+    }
+
+    fn solve_trips(&self, trips: usize) -> usize {
+        self.trip_times(trips).last().copied().unwrap_or(0)
     }
 }
 
+fn trip_times(input: &str, trips: usize) -> Vec<usize> {
+    Board::new(input).trip_times(trips)
+}
+
+fn solve_trips(input: &str, trips: usize) -> usize {
+    trip_times(input, trips).last().copied().unwrap_or(0)
+}
+
+pub(crate) fn visualize(input: &str) -> String {
+    let board = Board::new(input);
+    board.render_at(board.solve::<1>())
+}
+
 pub(crate) fn solve(input: &str) -> usize {
-    Board::new(input).solve::<1>()
+    solve_trips(input, 1)
 }
 
 pub(crate) fn solve_2(input: &str) -> usize {
-    Board::new(input).solve::<3>()
+    solve_trips(input, 3)
 }
 
 #[cfg(test)]
@@ -164,6 +302,60 @@ mod tests {
         ######.#
     ";
 
+    #[test]
+    fn test_render_at() {
+        let board = Board::new(EXAMPLE);
+        assert_eq!(
+            board.render_at(0),
+            ">>.<^<\n.<..<<\n>v.><>\n<^v^^>\n"
+        );
+        assert_eq!(
+            board.render_at(1),
+            ".>3.<.\n<..<<.\n>2.22.\n>v..^<\n"
+        );
+    }
+
+    #[test]
+    fn test_blizzard_speeds() {
+        let input = "
+            #.#####
+            #.>...#
+            #.....#
+            #####.#
+        ";
+        let blocked = |board: &Board, time| (0..5).find(|&x| !board.is_clear(time, x, 0));
+        let board = Board::new(input);
+        let positions = (0..6).map(|time| blocked(&board, time)).collect_vec();
+        assert_eq!(positions, [1, 2, 3, 4, 0, 1].map(Some));
+        let fast = Board::with_speeds(input, |_, _| 2);
+        let positions = (0..6).map(|time| blocked(&fast, time)).collect_vec();
+        assert_eq!(positions, [1, 3, 0, 2, 4, 1].map(Some));
+        assert_eq!(fast.render_at(2), ">....\n.....\n");
+
+        // Waiting just inside the entrance is only safe from the slow blizzard
+        let waiting = State {
+            time: 1,
+            pos: (0, 0),
+        };
+        let stays = |board: &Board| board.next_states(waiting).any(|s| s.pos == (0, 0));
+        assert!(stays(&board));
+        assert!(!stays(&fast));
+        assert_eq!(board.solve::<1>(), 7);
+    }
+
+    #[test]
+    fn test_cached_clearance() {
+        let board = Board::new(EXAMPLE);
+        let (width, height) = (board.ver_winds.len(), board.hor_winds.len());
+        for time in 0..3 * width * height {
+            for (x, y) in (0..width).cartesian_product(0..height) {
+                let uncached = board.hor_winds[y].iter().all(|w| w.is_clear(time, x))
+                    && board.ver_winds[x].iter().all(|w| w.is_clear(time, y));
+                assert_eq!(board.is_clear(time, x, y), uncached, "{time} {x} {y}");
+            }
+        }
+    }
+
     #[test]
     fn test_solve() {
         assert_eq!(solve(EXAMPLE), 18);
@@ -173,4 +365,52 @@ mod tests {
     fn test_solve_2() {
         assert_eq!(solve_2(EXAMPLE), 54);
     }
+
+    #[test]
+    fn test_solve_trips() {
+        assert_eq!(solve_trips(EXAMPLE, 1), 18);
+        assert_eq!(solve_trips(EXAMPLE, 2), 41);
+        assert_eq!(solve_trips(EXAMPLE, 3), 54);
+    }
+
+    #[test]
+    fn test_trip_times() {
+        assert_eq!(trip_times(EXAMPLE, 3), vec![18, 41, 54]);
+        assert_eq!(trip_times(EXAMPLE, 1), vec![solve(EXAMPLE)]);
+        assert_eq!(trip_times(EXAMPLE, 0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_with_endpoints() {
+        let map = "
+            #.####
+            #....#
+            #....#
+            ###.##
+        ";
+        let board = Board::with_endpoints(map, (0, -1), (2, 2));
+        assert_eq!(board.solve::<1>(), 5);
+        assert_eq!(board.solve::<2>(), 10);
+        let board = Board::new(map);
+        assert_eq!((board.start_pos, board.end_pos), ((0, -1), (2, 2)));
+        assert_eq!(board.solve::<1>(), 5);
+
+        let board = Board::with_endpoints(EXAMPLE, (0, -1), (2, 4));
+        let time = board.solve::<1>();
+        assert_eq!(time, board.fastest_path_dijkstra((0, -1), (2, 4), 0));
+        assert_ne!(time, solve(EXAMPLE));
+    }
+
+    #[test]
+    fn test_fastest_path_dijkstra() {
+        let board = Board::new(EXAMPLE);
+        let (start, end) = (board.start_pos, board.end_pos);
+        assert_eq!(board.fastest_path_dijkstra(start, end, 0), 18);
+        for (pos, end, time) in [(start, end, 0), (end, start, 18), (start, end, 41)] {
+            assert_eq!(
+                board.fastest_path(pos, end, time),
+                board.fastest_path_dijkstra(pos, end, time)
+            );
+        }
+    }
 }
@@ -1,7 +1,36 @@
-fn to_snafu(mut num: i64) -> String {
+use std::fmt::Display;
+
+use crate::utils::clean_lines;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SnafuError {
+    InvalidDigit { character: char, position: usize },
+    OutOfRange(String),
+}
+
+impl Display for SnafuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnafuError::InvalidDigit {
+                character,
+                position,
+            } => write!(
+                f,
+                "invalid SNAFU digit {character:?} at position {position}"
+            ),
+            SnafuError::OutOfRange(snafu) => write!(f, "SNAFU number {snafu:?} overflows i64"),
+        }
+    }
+}
+
+pub(crate) fn to_snafu(mut num: i128) -> String {
+    if num == 0 {
+        return "0".into();
+    }
     let mut result = String::new();
     while num != 0 {
-        let digit = match (num + 2) % 5 - 2 {
+        let value = (num + 2).rem_euclid(5) - 2;
+        let digit = match value {
             -2 => '=',
             -1 => '-',
             0 => '0',
@@ -10,42 +39,57 @@ fn to_snafu(mut num: i64) -> String {
             _ => panic!(),
         };
         result.insert(0, digit);
-        num = (num + 2) / 5;
+        num = (num - value) / 5;
     }
     result
 }
 
-fn from_snafu(snafu: &str) -> i64 {
-    let mut result = 0;
-    for (place, c) in snafu.chars().rev().enumerate() {
-        let digit = match c {
-            '=' => -2,
-            '-' => -1,
-            '0' => 0,
-            '1' => 1,
-            '2' => 2,
-            _ => panic!(),
-        };
-        result += digit * 5i64.pow(place as u32);
-    }
-    result
+pub(crate) fn from_snafu(snafu: &str) -> Result<i64, SnafuError> {
+    // Leading '-' is already the digit -1, so negative numbers need no sign
+    let offset = snafu.chars().take_while(|c| c.is_whitespace()).count();
+    let digits = snafu
+        .trim()
+        .chars()
+        .enumerate()
+        .map(|(i, c)| (offset + i, c))
+        .map(|(position, c)| match c {
+            '=' => Ok(-2),
+            '-' => Ok(-1),
+            '0' => Ok(0),
+            '1' => Ok(1),
+            '2' => Ok(2),
+            _ => Err(SnafuError::InvalidDigit {
+                character: c,
+                position,
+            }),
+        })
+        .collect::<Result<Vec<i64>, _>>()?;
+    // Partial sums can leave i64 even when the total fits, so they're kept in i128
+    digits
+        .into_iter()
+        .try_fold(0i128, |total, digit| {
+            total.checked_mul(5)?.checked_add(digit.into())
+        })
+        .and_then(|total| i64::try_from(total).ok())
+        .ok_or_else(|| SnafuError::OutOfRange(snafu.trim().into()))
 }
 
-pub(crate) fn solve(input: &str) -> String {
-    to_snafu(
-        input
-            .lines()
-            .map(|l| l.trim())
-            .filter(|l| !l.is_empty())
-            .map(from_snafu)
-            .sum(),
-    )
+pub(crate) fn solve(input: &str) -> Result<String, SnafuError> {
+    let total = clean_lines(input)
+        .map(|l| from_snafu(l).map(i128::from))
+        .sum::<Result<i128, _>>()?;
+    Ok(to_snafu(total))
+}
+
+pub(crate) fn solve_2(_input: &str) -> String {
+    "Start the blender!".into()
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::utils::Rng;
 
     const EXAMPLE: &str = "
         1=-0-2
@@ -65,6 +109,99 @@ mod tests {
 
     #[test]
     fn test_solve() {
-        assert_eq!(solve(EXAMPLE), "2=-1=0")
+        assert_eq!(solve(EXAMPLE).unwrap(), "2=-1=0")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for line in EXAMPLE.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            assert_eq!(to_snafu(from_snafu(line).unwrap().into()), line);
+        }
+        assert_eq!(from_snafu("1=-0-2"), Ok(1747));
+        assert_eq!(to_snafu(4890), "2=-1=0");
+    }
+
+    #[test]
+    fn test_zero() {
+        assert_eq!(to_snafu(0), "0");
+        assert_eq!(from_snafu("0"), Ok(0));
+        assert_eq!(solve("1\n-").unwrap(), "0");
+    }
+
+    #[test]
+    fn test_round_trip_property() {
+        let check = |n: i64| {
+            let snafu = to_snafu(n.into());
+            assert_eq!(from_snafu(&snafu), Ok(n), "{snafu}");
+            assert!(snafu == "0" || !snafu.starts_with('0'), "{n} -> {snafu}");
+        };
+        (-5000..=5000).for_each(check);
+        // Quartering keeps the top digit's weight within i64
+        let mut rng = Rng::new(25);
+        for _ in 0..10_000 {
+            check(rng.next_u64() as i64 / 4);
+        }
+    }
+
+    #[test]
+    fn test_large_sum() {
+        let max = to_snafu(i64::MAX.into());
+        assert_eq!(from_snafu(&max), Ok(i64::MAX));
+        let input = [max.as_str(), max.as_str(), "1"].join("\n");
+        assert_eq!(solve(&input).unwrap(), to_snafu(2 * i64::MAX as i128 + 1));
+    }
+
+    #[test]
+    fn test_invalid_digit() {
+        assert_eq!(
+            from_snafu("1=3-"),
+            Err(SnafuError::InvalidDigit {
+                character: '3',
+                position: 2
+            })
+        );
+        assert_eq!(
+            solve("1=\n12\n2=3").unwrap_err().to_string(),
+            "invalid SNAFU digit '3' at position 2"
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        // 28 ones is just over i64::MAX
+        let long = "1".repeat(28);
+        assert_eq!(from_snafu(&long), Err(SnafuError::OutOfRange(long.clone())));
+        let max = to_snafu(i64::MAX.into());
+        let min = to_snafu(i64::MIN.into());
+        assert_eq!(from_snafu(&min), Ok(i64::MIN));
+        assert!(from_snafu(&"2".repeat(60)).is_err());
+        let over = to_snafu(i64::MAX as i128 + 1);
+        assert_eq!(from_snafu(&over), Err(SnafuError::OutOfRange(over.clone())));
+        assert_eq!(
+            solve(&format!("{max}\n{long}")).unwrap_err().to_string(),
+            format!("SNAFU number \"{long}\" overflows i64")
+        );
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(from_snafu("  1=-0-2\t"), Ok(1747));
+        assert_eq!(
+            from_snafu(" 1 2"),
+            Err(SnafuError::InvalidDigit {
+                character: ' ',
+                position: 2
+            })
+        );
+        assert_eq!(solve("  1=\n\t12  \n").unwrap(), "20");
+    }
+
+    #[test]
+    fn test_negative() {
+        assert_eq!(from_snafu("-1"), Ok(-4));
+        assert_eq!(from_snafu("=-2"), Ok(-53));
+        assert_eq!(to_snafu(-1747), "-2101=");
+        assert_eq!(from_snafu("-2101="), Ok(-1747));
+        assert_eq!(solve("1=-0-2\n-2101=\n-").unwrap(), "-");
     }
 }
@@ -2,10 +2,21 @@ use std::collections::HashSet;
 
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct Item(char);
 
 impl Item {
+    // Only ASCII letters are items; anything else is handed back
+    fn from_byte(b: u8) -> Result<Item, u8> {
+        if b.is_ascii_alphabetic() {
+            Ok(Item(b as char))
+        } else {
+            Err(b)
+        }
+    }
+
     fn priority(self) -> i32 {
         match self.0 {
             'a'..='z' => self.0 as i32 - 'a' as i32 + 1,
@@ -15,13 +26,15 @@ impl Item {
     }
 }
 
-fn parse(input: &str) -> impl Iterator<Item = Vec<Item>> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .map(|l| l.bytes().map(|b| Item(b as char)))
-        .map(|items| -> Vec<_> { items.collect() })
+// Fails with the first byte that isn't an item
+fn parse(input: &str) -> Result<Vec<Vec<Item>>, u8> {
+    clean_lines(input)
+        .map(|l| l.bytes().map(Item::from_byte).collect())
+        .collect()
+}
+
+fn invalid_item(b: u8) -> String {
+    format!("invalid item {:?} (byte {b:#04x})", b as char)
 }
 
 fn pairs(
@@ -33,11 +46,12 @@ fn pairs(
     })
 }
 
-pub(crate) fn solve(input: &str) -> i32 {
-    let rucksack_pairs = pairs(parse(input));
-    get_shared_item(rucksack_pairs)
+pub(crate) fn solve(input: &str) -> Result<i32, String> {
+    let rucksacks = parse(input).map_err(invalid_item)?;
+    let rucksack_pairs = pairs(rucksacks.into_iter());
+    Ok(get_shared_item(rucksack_pairs)
         .map(|item| item.priority())
-        .sum()
+        .sum())
 }
 
 fn get_shared_item(
@@ -64,11 +78,11 @@ fn get_shared_item_2(
         })
 }
 
-pub(crate) fn solve_2(input: &str) -> i32 {
-    let rucksacks = parse(input);
-    get_shared_item_2(rucksacks)
+pub(crate) fn solve_2(input: &str) -> Result<i32, String> {
+    let rucksacks = parse(input).map_err(invalid_item)?;
+    Ok(get_shared_item_2(rucksacks.into_iter())
         .map(|item| item.priority())
-        .sum()
+        .sum())
 }
 
 #[cfg(test)]
@@ -77,13 +91,13 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let result: Vec<_> = parse(
+        let result = parse(
             "
             aA
             bbBB
         ",
         )
-        .collect();
+        .unwrap();
         assert_eq!(
             result,
             vec![
@@ -93,6 +107,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(Item::from_byte(b'q'), Ok(Item('q')));
+        assert_eq!(Item::from_byte(b'1'), Err(b'1'));
+        assert_eq!(parse("ab\nc-d\n"), Err(b'-'));
+        assert_eq!(parse("abé"), Err(0xc3));
+        assert_eq!(
+            solve("vJrwpWtwJgWr_hcsFMMfFFhFp").unwrap_err(),
+            "invalid item '_' (byte 0x5f)"
+        );
+        assert!(solve_2("a b\nab\nab").is_err());
+    }
+
     #[test]
     fn test_priority() {
         assert_eq!(Item('a').priority(), 1);
@@ -103,7 +130,7 @@ mod tests {
 
     #[test]
     fn test_solve_1() {
-        let rucksacks = pairs(parse("aaba"));
+        let rucksacks = pairs(parse("aaba").unwrap().into_iter());
         let items: Vec<_> = get_shared_item(rucksacks).collect();
         assert_eq!(items, vec![Item('a')]);
     }
@@ -120,8 +147,9 @@ mod tests {
             dab
             fgb
         ",
-        );
-        let items: Vec<_> = get_shared_item_2(rucksacks).collect();
+        )
+        .unwrap();
+        let items: Vec<_> = get_shared_item_2(rucksacks.into_iter()).collect();
         assert_eq!(items, vec![Item('a'), Item('b')]);
     }
 }
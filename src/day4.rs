@@ -1,4 +1,4 @@
-use itertools::Itertools;
+use crate::utils::{clean_lines, parse_ints, ParseError};
 
 #[derive(Debug, PartialEq)]
 struct Range {
@@ -37,30 +37,45 @@ impl<T: Iterator> CountTrue for T {
     }
 }
 
-fn parse(input: &str) -> impl Iterator<Item = (Range, Range)> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .flat_map(|l| l.split(','))
-        .flat_map(|range| range.split('-'))
-        .map(|s| s.parse::<usize>().unwrap())
-        .tuples()
-        .map(|(start, end)| Range { start, end })
-        .tuples()
-        .map(|(l, r)| (l, r))
+const EXPECTED: &str = "`<start>-<end>,<start>-<end>` with non-negative numbers";
+
+fn parse(input: &str) -> impl Iterator<Item = Result<(Range, Range), ParseError>> + '_ {
+    clean_lines(input).map(|l| {
+        let error = || ParseError::new(l, EXPECTED);
+        // `parse_ints` skips whatever is between the numbers, so check for junk here
+        let is_junk = |c: char| !(c.is_ascii_digit() || c == '-' || c == ',');
+        if l.chars().any(is_junk) {
+            return Err(error());
+        }
+        let ints = parse_ints(l).map_err(|_| error())?;
+        let ints = ints
+            .into_iter()
+            .map(usize::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error())?;
+        match ints[..] {
+            [a, b, c, d] => Ok((Range { start: a, end: b }, Range { start: c, end: d })),
+            _ => Err(error()),
+        }
+    })
 }
 
-pub(crate) fn solve(input: &str) -> i32 {
-    parse(input).count_true(|(l, r)| l.contains_range(&r) || r.contains_range(&l))
+pub(crate) fn solve(input: &str) -> Result<i32, ParseError> {
+    let pairs = parse(input).collect::<Result<Vec<_>, _>>()?;
+    Ok(pairs
+        .into_iter()
+        .count_true(|(l, r)| l.contains_range(&r) || r.contains_range(&l)))
 }
 
-pub(crate) fn solve_2(input: &str) -> i32 {
-    parse(input).count_true(|(l, r)| l.overlaps_range(&r))
+pub(crate) fn solve_2(input: &str) -> Result<i32, ParseError> {
+    let pairs = parse(input).collect::<Result<Vec<_>, _>>()?;
+    Ok(pairs.into_iter().count_true(|(l, r)| l.overlaps_range(&r)))
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     #[test]
@@ -72,7 +87,8 @@ mod tests {
             5-7,7-9
         ",
         )
-        .collect();
+        .collect::<Result<_, _>>()
+        .unwrap();
         assert_eq!(
             result,
             vec![
@@ -83,17 +99,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_errors() {
+        for line in [
+            "-2-4,6-8",
+            "2-4,6",
+            "2-4,6-8,1",
+            "2-4,6-8x",
+            "2-4,6-99999999999999999999",
+        ] {
+            assert_eq!(
+                parse(line).collect_vec(),
+                vec![Err(ParseError::new(line, EXPECTED))],
+                "{line}"
+            );
+        }
+        assert!(solve("2-4,6-8\n2-3").is_err());
+    }
+
     #[test]
     fn test_solve() {
-        assert_eq!(solve("2-4,2-4"), 1);
-        assert_eq!(solve("2-6,6-8"), 0);
-        assert_eq!(solve("2-4,6-8"), 0);
+        assert_eq!(solve("2-4,2-4"), Ok(1));
+        assert_eq!(solve("2-6,6-8"), Ok(0));
+        assert_eq!(solve("2-4,6-8"), Ok(0));
     }
 
     #[test]
     fn test_solve_2() {
-        assert_eq!(solve_2("2-4,2-4"), 1);
-        assert_eq!(solve_2("2-6,6-8"), 1);
-        assert_eq!(solve_2("2-4,6-8"), 0);
+        assert_eq!(solve_2("2-4,2-4"), Ok(1));
+        assert_eq!(solve_2("2-6,6-8"), Ok(1));
+        assert_eq!(solve_2("2-4,6-8"), Ok(0));
     }
 }
@@ -1,5 +1,9 @@
+use std::fmt::Write;
+
 use itertools::Itertools;
 
+use crate::utils::Rng;
+
 #[derive(Debug, PartialEq)]
 struct Instruction1 {
     count: usize,
@@ -25,6 +29,10 @@ impl Instruction for Instruction1 {
     }
 
     fn apply(&self, state: &mut State) {
+        // Moving nothing leaves every stack, even an empty source, untouched
+        if self.count == 0 {
+            return;
+        }
         // Account for 1-based indexes
         let (from, to) = (self.from - 1, self.to - 1);
         let from_vec = &mut state[from];
@@ -40,6 +48,10 @@ impl Instruction for Instruction2 {
     }
 
     fn apply(&self, state: &mut State) {
+        // Moving nothing leaves every stack, even an empty source, untouched
+        if self.count == 0 {
+            return;
+        }
         // Account for 1-based indexes
         let (from, to) = (self.from - 1, self.to - 1);
         let from_vec = &mut state[from];
@@ -64,27 +76,43 @@ fn parse_instructions<'a, I: Instruction>(
 fn parse_state<'a, 'b>(lines: &'a mut impl Iterator<Item = &'b str>) -> State {
     let mut state_lines = Vec::new();
     while let Some(line) = lines.next() {
-        if line.is_empty() {
+        if line.trim().is_empty() {
             break;
         }
         state_lines.push(line);
     }
-    state_lines.reverse();
-    state_lines
-        .iter()
-        .flat_map(|l| l.chars().enumerate())
-        .filter(|(_, c)| c.is_alphabetic())
-        .into_group_map()
-        .into_iter()
-        .sorted_by_key(|(i, _)| *i)
-        .map(|(_, v)| v)
-        .collect()
+    // The numbered base row gives the stack count, even for empty stacks
+    let base = state_lines.pop().unwrap_or_default();
+    let mut state = vec![Vec::new(); base.split_whitespace().count()];
+    // Its first number sits one column into the drawing, so anything before
+    // that is indentation. Rows keep the rest of their leading spaces, since
+    // those stand for empty stacks.
+    let indent = base.len() - base.trim_start().len();
+    let indent = indent.saturating_sub(1);
+    // Columns are counted from the left, so right-trimmed rows need no padding
+    for line in state_lines.iter().rev() {
+        let leading = line.len() - line.trim_start().len();
+        let line = &line[leading.min(indent)..];
+        for (i, c) in line.chars().enumerate().filter(|(_, c)| c.is_alphabetic()) {
+            // A crate past the last number still gets its own stack
+            if i / 4 >= state.len() {
+                state.resize(i / 4 + 1, Vec::new());
+            }
+            state[i / 4].push(c);
+        }
+    }
+    state
 }
 
 fn parse<I: Instruction + 'static>(input: &str) -> (State, impl Iterator<Item = I> + '_) {
-    let mut lines = input.lines().map(|l| l.trim());
+    let mut lines = input.lines().skip_while(|l| l.trim().is_empty());
     let setup = parse_state(&mut lines);
-    let instructions = parse_instructions(lines);
+    // The drawing ends at a blank line, so only the moves skip comments
+    let instructions = parse_instructions(
+        lines
+            .map(|l| l.trim())
+            .filter(|l| !l.starts_with('#')),
+    );
     (setup, instructions)
 }
 
@@ -98,6 +126,49 @@ fn compute<I: Instruction + 'static>(input: &str) -> State {
     })
 }
 
+// Random stacks followed by moves that are always possible
+pub(crate) fn generate(rng: &mut Rng) -> String {
+    let num_stacks = rng.range(1..10);
+    let mut stacks: State = (0..num_stacks)
+        .map(|_| {
+            (0..rng.range(0..8))
+                .map(|_| (b'A' + rng.range(0..26) as u8) as char)
+                .collect()
+        })
+        .collect();
+    let max_height = stacks.iter().map(Vec::len).max().unwrap();
+
+    let mut input = String::new();
+    for row in (0..max_height).rev() {
+        let line = stacks
+            .iter()
+            .map(|stack| match stack.get(row) {
+                Some(c) => format!("[{c}]"),
+                None => "   ".into(),
+            })
+            .join(" ");
+        writeln!(input, "{}", line.trim_end()).unwrap();
+    }
+    writeln!(input, "{}\n", (1..=num_stacks).map(|i| format!(" {i} ")).join(" ")).unwrap();
+
+    for _ in 0..rng.range(0..20) {
+        let nonempty = (0..num_stacks)
+            .filter(|&i| !stacks[i].is_empty())
+            .collect_vec();
+        if num_stacks < 2 || nonempty.is_empty() {
+            break;
+        }
+        let from = nonempty[rng.range(0..nonempty.len())];
+        let to = (from + rng.range(1..num_stacks)) % num_stacks;
+        let count = rng.range(1..stacks[from].len() + 1);
+        let from_vec = &mut stacks[from];
+        let moving = from_vec.split_off(from_vec.len() - count);
+        stacks[to].extend(moving);
+        writeln!(input, "move {count} from {} to {}", from + 1, to + 1).unwrap();
+    }
+    input
+}
+
 pub(crate) fn solve(input: &str) -> String {
     compute::<Instruction1>(input)
         .iter()
@@ -156,6 +227,117 @@ mod tests {
         assert_eq!(state, vec![vec!['H', 'B'], vec!['M'], vec!['N', 'D'],]);
     }
 
+    #[test]
+    fn test_comments() {
+        let input = "\
+            [B]     [D]
+            [H] [M] [N]
+             1   2   3
+
+            # 12 is not a move
+            move 1 from 2 to 3
+        ";
+        assert_eq!(solve(input), "B M");
+    }
+
+    #[test]
+    fn test_state_parse_empty_stack() {
+        let mut input = "\
+            [A]     [C]
+            [B]     [D]
+             1   2   3   4
+        "
+        .lines()
+        .map(|l| l.trim());
+        let state = parse_state(&mut input);
+        assert_eq!(state, vec![vec!['B', 'A'], vec![], vec!['D', 'C'], vec![]]);
+    }
+
+    #[test]
+    fn test_state_parse_empty_first_stack() {
+        let input = "
+                    [C]
+            [A]     [B]
+                [D] [E]
+             1   2   3
+
+            move 1 from 3 to 1
+        ";
+        let state = compute::<Instruction1>(input);
+        assert_eq!(state, vec![vec!['A', 'C'], vec!['D'], vec!['E', 'B']]);
+        let mut unindented = ["        [C]", "    [D] [E]", " 1   2   3"].into_iter();
+        let state = parse_state(&mut unindented);
+        assert_eq!(state, vec![vec![], vec!['D'], vec!['E', 'C']]);
+    }
+
+    #[test]
+    fn test_state_parse_wider_than_base() {
+        let mut input = ["        [C]", "[A]     [B]", " 1   2 "].into_iter();
+        let state = parse_state(&mut input);
+        assert_eq!(state, vec![vec!['A'], vec![], vec!['B', 'C']]);
+    }
+
+    #[test]
+    fn test_state_parse_right_trimmed() {
+        let mut input = [
+            "[Z]",
+            "[Y]     [X]",
+            "[W] [V] [U]     [T]",
+            " 1   2   3   4   5 ",
+        ]
+        .into_iter();
+        let state = parse_state(&mut input);
+        assert_eq!(
+            state,
+            vec![
+                vec!['W', 'Y', 'Z'],
+                vec!['V'],
+                vec!['U', 'X'],
+                vec![],
+                vec!['T']
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_zero() {
+        let mut state = vec![vec!['A'], vec![]];
+        Instruction1::new(0, 2, 1).apply(&mut state);
+        Instruction2::new(0, 2, 1).apply(&mut state);
+        Instruction1::new(0, 1, 2).apply(&mut state);
+        assert_eq!(state, vec![vec!['A'], vec![]]);
+    }
+
+    #[test]
+    fn test_solve_move_zero() {
+        let input = "\
+            [B]     [D]
+            [H] [M] [N]
+             1   2   3
+
+            move 1 from 2 to 3
+            move 0 from 2 to 1
+            move 2 from 3 to 1
+        ";
+        let without = input.replace("move 0 from 2 to 1\n", "");
+        assert_eq!(solve(input), solve(&without));
+        assert_eq!(solve_2(input), solve_2(&without));
+        assert_eq!(solve(input), "D N");
+    }
+
+    #[test]
+    fn test_generate() {
+        for seed in 0..100 {
+            let input = generate(&mut Rng::new(seed));
+            let crates = input.chars().filter(char::is_ascii_uppercase).count();
+            let state = compute::<Instruction1>(&input);
+            assert_eq!(state.iter().map(Vec::len).sum::<usize>(), crates, "{input}");
+            assert_eq!(solve(&input).len(), state.len(), "{input}");
+            assert_eq!(solve_2(&input).len(), state.len(), "{input}");
+        }
+        assert_eq!(generate(&mut Rng::new(7)), generate(&mut Rng::new(7)));
+    }
+
     #[test]
     fn test_solve() {
         // Initial state
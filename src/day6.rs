@@ -1,5 +1,9 @@
+use std::{collections::HashMap, iter::zip};
+
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 pub(crate) fn solve(input: &str) -> usize {
     compute::<4>(input)
 }
@@ -8,18 +12,41 @@ pub(crate) fn solve_2(input: &str) -> usize {
     compute::<14>(input)
 }
 
-fn compute<const N: usize>(input: &str) -> usize {
-    let mut window = Vec::new();
-    for (i, c) in input.chars().enumerate() {
-        window.push(c);
-        if window.len() > N {
-            window.remove(0);
+// Position just after the first N distinct characters, in one pass: the window
+// start jumps past the previous occurrence of each repeated character
+fn find_marker<const N: usize>(stream: &str) -> Option<usize> {
+    let mut last_seen = HashMap::new();
+    let mut start = 0;
+    for (i, c) in stream.chars().enumerate() {
+        if let Some(prev) = last_seen.insert(c, i) {
+            start = start.max(prev + 1);
         }
-        if window.iter().unique().count() == N {
-            return i + 1;
+        if i + 1 - start == N {
+            return Some(i + 1);
         }
     }
-    panic!("Didn't find marker");
+    None
+}
+
+fn compute<const N: usize>(input: &str) -> usize {
+    find_marker::<N>(input).expect("Didn't find marker")
+}
+
+// One marker per datastream line
+fn solve_lines<const N: usize>(input: &str) -> impl Iterator<Item = Option<usize>> + '_ {
+    clean_lines(input).map(find_marker::<N>)
+}
+
+// Both markers for every line, for inputs holding several datastreams
+pub(crate) fn report(input: &str) -> String {
+    let show = |marker: Option<usize>| marker.map_or("none".to_string(), |m| m.to_string());
+    zip(solve_lines::<4>(input), solve_lines::<14>(input))
+        .enumerate()
+        .map(|(i, (packet, message))| {
+            let (packet, message) = (show(packet), show(message));
+            format!("Line {}: packet {packet}, message {message}", i + 1)
+        })
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -34,6 +61,25 @@ mod tests {
         assert_eq!(compute::<4>("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 11);
     }
 
+    #[test]
+    fn test_solve_lines() {
+        let input = "
+            bvwbjplbgvbhsrlpgdmjqwftvncz
+            aaaaaaaa
+            nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg
+        ";
+        let markers = solve_lines::<4>(input).collect::<Vec<_>>();
+        assert_eq!(markers, vec![Some(5), None, Some(10)]);
+        assert_eq!(
+            solve_lines::<1>("a\nb\n").collect::<Vec<_>>(),
+            vec![Some(1); 2]
+        );
+        assert_eq!(
+            report(input),
+            "Line 1: packet 5, message 23\nLine 2: packet none, message none\nLine 3: packet 10, message 29"
+        );
+    }
+
     #[test]
     fn test_2() {
         assert_eq!(compute::<14>("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), 19);
@@ -2,6 +2,8 @@ use std::{collections::HashMap, iter::Peekable, path::PathBuf};
 
 use itertools::Itertools;
 
+use crate::utils::clean_lines;
+
 struct File {
     size: usize,
     _name: String,
@@ -143,40 +145,33 @@ enum Token {
     Dir,
 }
 
+// Names are everything after the first space, so they may contain spaces
 fn tokenise(input: &str) -> impl Iterator<Item = Token> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .flat_map(|l| {
-            let mut it = l
-                .split_ascii_whitespace()
-                .filter(|s| !s.is_empty())
-                .peekable();
             let mut tokens = Vec::new();
-            if let Some(&"$") = it.peek() {
-                it.next();
-                if let Some("ls") = it.next() {
+            if let Some(command) = l.strip_prefix("$ ") {
+                if command == "ls" {
                     tokens.push(Token::LS);
                 } else {
+                    let dir = command.strip_prefix("cd ").unwrap();
                     tokens.push(Token::CD);
-                    tokens.push(Token::Text(it.next().unwrap().into()));
-                    assert!(it.next().is_none());
+                    tokens.push(Token::Text(dir.into()));
                 }
             } else {
-                if let Some(&"dir") = it.peek() {
-                    it.next();
+                let (kind, name) = l.split_once(' ').unwrap();
+                if kind == "dir" {
                     tokens.push(Token::Dir);
                 } else {
-                    tokens.push(Token::Number(it.next().unwrap().parse::<usize>().unwrap()));
+                    tokens.push(Token::Number(kind.parse::<usize>().unwrap()));
                 }
-                tokens.push(Token::Text(it.next().unwrap().into()));
+                tokens.push(Token::Text(name.into()));
             }
             tokens.into_iter()
         })
 }
 
-pub(crate) fn solve(input: &str) -> usize {
+fn sizes(input: &str) -> HashMap<PathBuf, usize> {
     tokenise(input)
         .commands()
         .fold(VM::new(), |mut vm, cmd| {
@@ -184,20 +179,30 @@ pub(crate) fn solve(input: &str) -> usize {
             vm
         })
         .calculate_sizes()
-        .values()
-        .filter(|s| **s <= 100000)
-        .sum()
+}
+
+fn used_space(sizes: &HashMap<PathBuf, usize>) -> usize {
+    *sizes.get(&PathBuf::from("/")).unwrap()
+}
+
+// (used, free) on a disk of size `total`
+fn disk_usage(input: &str, total: usize) -> (usize, usize) {
+    let used = used_space(&sizes(input));
+    (used, total - used)
+}
+
+pub(crate) fn report(input: &str) -> String {
+    let (used, free) = disk_usage(input, 70000000);
+    format!("{used} used, {free} free")
+}
+
+pub(crate) fn solve(input: &str) -> usize {
+    sizes(input).values().filter(|s| **s <= 100000).sum()
 }
 
 pub(crate) fn solve_2(input: &str) -> usize {
-    let sizes = tokenise(input)
-        .commands()
-        .fold(VM::new(), |mut vm, cmd| {
-            vm.execute(cmd);
-            vm
-        })
-        .calculate_sizes();
-    let used_space = *sizes.get(&PathBuf::from("/")).unwrap();
+    let sizes = sizes(input);
+    let used_space = used_space(&sizes);
     let total_space = 70000000;
     let ideal_free_space = 30000000;
     let ideal_used_space = total_space - ideal_free_space;
@@ -242,4 +247,67 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_spaces_in_names() {
+        let input = "
+            $ cd /
+            $ ls
+            dir my dir
+            10 my file.txt
+            $ cd my dir
+            $ ls
+            20 another  file
+        ";
+        let tokens = tokenise(input).collect_vec();
+        assert_eq!(tokens[4], Token::Text("my dir".into()));
+        assert_eq!(tokens[6], Token::Text("my file.txt".into()));
+        assert_eq!(tokens[8], Token::Text("my dir".into()));
+        assert_eq!(tokens[11], Token::Text("another  file".into()));
+        let sizes = sizes(input);
+        assert_eq!(sizes[&PathBuf::from("/my dir")], 20);
+        assert_eq!(used_space(&sizes), 30);
+    }
+
+    const EXAMPLE: &str = "
+        $ cd /
+        $ ls
+        dir a
+        14848514 b.txt
+        8504156 c.dat
+        dir d
+        $ cd a
+        $ ls
+        dir e
+        29116 f
+        2557 g
+        62596 h.lst
+        $ cd e
+        $ ls
+        584 i
+        $ cd ..
+        $ cd ..
+        $ cd d
+        $ ls
+        4060174 j
+        8033020 d.log
+        5626152 d.ext
+        7214296 k
+    ";
+
+    #[test]
+    fn test_disk_usage() {
+        assert_eq!(disk_usage(EXAMPLE, 70000000), (48381165, 21618835));
+        assert_eq!(report(EXAMPLE), "48381165 used, 21618835 free");
+    }
+
+    #[test]
+    fn test_solve() {
+        assert_eq!(solve(EXAMPLE), 95437);
+    }
+
+    #[test]
+    fn test_solve_2() {
+        assert_eq!(solve_2(EXAMPLE), 24933642);
+    }
 }
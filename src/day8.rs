@@ -2,6 +2,8 @@ use std::iter::empty;
 
 use itertools::{FoldWhile, Itertools};
 
+use crate::utils::{clean_lines, Grid};
+
 #[derive(Debug, PartialEq)]
 struct Tree {
     height: u8,
@@ -9,32 +11,55 @@ struct Tree {
 }
 
 struct Forest {
-    trees: Vec<Vec<Tree>>,
+    trees: Grid<Tree>,
 }
 
 impl Forest {
-    fn new(heights: Vec<Vec<u8>>) -> Self {
+    // Rows of tree heights, top to bottom
+    pub(crate) fn from_grid(heights: Vec<Vec<u8>>) -> Self {
         Forest {
-            trees: heights
-                .into_iter()
-                .enumerate()
-                .map(|(y, row)| {
-                    row.into_iter()
-                        .enumerate()
-                        .map(|(x, height)| Tree {
-                            position: (x, y),
-                            height,
-                        })
-                        .collect()
-                })
-                .collect(),
+            trees: Grid::new(
+                heights
+                    .into_iter()
+                    .enumerate()
+                    .map(|(y, row)| {
+                        row.into_iter()
+                            .enumerate()
+                            .map(|(x, height)| Tree {
+                                position: (x, y),
+                                height,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            ),
         }
     }
 
     fn size(&self) -> (usize, usize) {
-        let y_size = self.trees.len();
-        let x_size = self.trees.first().map_or(0, |row| row.len());
-        (x_size, y_size)
+        self.trees.size()
+    }
+
+    fn heights(&self) -> Vec<Vec<u8>> {
+        let (x_size, y_size) = self.size();
+        (0..y_size)
+            .map(|y| (0..x_size).map(|x| self.trees[(x, y)].height).collect())
+            .collect()
+    }
+
+    // Swaps rows and columns, so `Up`/`Down` lines become `Left`/`Right` lines
+    #[cfg(test)]
+    fn transpose(&self) -> Forest {
+        let (x_size, y_size) = self.size();
+        Forest::from_grid(
+            (0..x_size)
+                .map(|x| {
+                    (0..y_size)
+                        .map(|y| self.trees.get((x, y)).unwrap().height)
+                        .collect()
+                })
+                .collect(),
+        )
     }
 
     fn trees(&self, line: Line) -> impl Iterator<Item = &'_ Tree> {
@@ -45,6 +70,7 @@ impl Forest {
         }
     }
 
+    #[cfg(test)]
     fn visible_trees(&self, line: Line) -> impl Iterator<Item = &'_ Tree> {
         let mut maybe_prev: Option<&Tree> = None;
         self.trees(line).filter(move |tree| match maybe_prev {
@@ -107,26 +133,23 @@ impl<'a> Iterator for LineIter<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         let (x_size, y_size) = self.forest.size();
         let (x, y) = match self.line {
-            Line::Right(y) => (self.pos as isize, y as isize),
-            Line::Left(y) => (x_size as isize - 1 - self.pos as isize, y as isize),
-            Line::Down(x) => (x as isize, self.pos as isize),
-            Line::Up(x) => (x as isize, y_size as isize - 1 - self.pos as isize),
+            Line::Right(y) => (self.pos, y),
+            Line::Left(y) => (x_size.wrapping_sub(1 + self.pos), y),
+            Line::Down(x) => (x, self.pos),
+            Line::Up(x) => (x, y_size.wrapping_sub(1 + self.pos)),
         };
-        if 0 <= x && x < x_size as isize && 0 <= y && y < y_size as isize {
-            self.pos += 1;
-            Some(&self.forest.trees[y as usize][x as usize])
-        } else {
-            None
-        }
+        let tree = self.forest.trees.get((x, y))?;
+        self.pos += 1;
+        Some(tree)
     }
 }
 
 fn parse(input: &str) -> Forest {
-    Forest::new(
-        input
-            .lines()
-            .map(|l| l.trim())
-            .filter(|l| !l.is_empty())
+    if input.contains(',') {
+        return parse_csv(input);
+    }
+    Forest::from_grid(
+        clean_lines(input)
             .map(|l| {
                 l.chars()
                     .map(|c| c.to_digit(10).unwrap() as u8)
@@ -136,8 +159,22 @@ fn parse(input: &str) -> Forest {
     )
 }
 
-pub(crate) fn solve(input: &str) -> usize {
-    let forest = parse(input);
+// Comma separated heights, e.g. `3,0,3,7,3`, allowing multiple digits
+fn parse_csv(input: &str) -> Forest {
+    Forest::from_grid(
+        clean_lines(input)
+            .map(|l| {
+                l.split(',')
+                    .map(|h| h.trim().parse::<u8>().unwrap())
+                    .collect_vec()
+            })
+            .collect_vec(),
+    )
+}
+
+// Walks in from every edge; `count_visible` is checked against this
+#[cfg(test)]
+fn compute(forest: &Forest) -> usize {
     let (x_size, y_size) = forest.size();
     empty()
         .chain((0..x_size).flat_map(|x| forest.visible_trees(Line::Up(x))))
@@ -148,8 +185,7 @@ pub(crate) fn solve(input: &str) -> usize {
         .count()
 }
 
-pub(crate) fn solve_2(input: &str) -> usize {
-    let forest = parse(input);
+fn compute_2(forest: &Forest) -> usize {
     let (x_size, y_size) = forest.size();
     empty()
         .chain((0..x_size).flat_map(|x| forest.view_distances(Line::Up(x))))
@@ -169,9 +205,61 @@ pub(crate) fn solve_2(input: &str) -> usize {
         .unwrap()
 }
 
+// Visible trees from four prefix-maximum grids, each the tallest tree so far when
+// coming in from one edge, so every tree is checked in constant time
+fn count_visible(heights: &[Vec<u8>]) -> usize {
+    let y_size = heights.len();
+    let x_size = heights.first().map_or(0, Vec::len);
+    let mut left = heights.to_vec();
+    let mut right = heights.to_vec();
+    let mut up = heights.to_vec();
+    let mut down = heights.to_vec();
+    for y in 0..y_size {
+        for x in 1..x_size {
+            left[y][x] = left[y][x].max(left[y][x - 1]);
+            let x = x_size - 1 - x;
+            right[y][x] = right[y][x].max(right[y][x + 1]);
+        }
+    }
+    for y in 1..y_size {
+        for x in 0..x_size {
+            down[y][x] = down[y][x].max(down[y - 1][x]);
+            let y = y_size - 1 - y;
+            up[y][x] = up[y][x].max(up[y + 1][x]);
+        }
+    }
+    (0..y_size)
+        .cartesian_product(0..x_size)
+        .filter(|&(y, x)| {
+            let height = heights[y][x];
+            x == 0
+                || y == 0
+                || x == x_size - 1
+                || y == y_size - 1
+                || left[y][x - 1] < height
+                || right[y][x + 1] < height
+                || down[y - 1][x] < height
+                || up[y + 1][x] < height
+        })
+        .count()
+}
+
+fn solve_prefix(input: &str) -> usize {
+    count_visible(&parse(input).heights())
+}
+
+pub(crate) fn solve(input: &str) -> usize {
+    solve_prefix(input)
+}
+
+pub(crate) fn solve_2(input: &str) -> usize {
+    compute_2(&parse(input))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::Rng;
 
     #[test]
     fn test_parse() {
@@ -181,12 +269,7 @@ mod tests {
             20
         ",
         );
-        let heights = forest
-            .trees
-            .iter()
-            .map(|row| row.iter().map(|t| t.height).collect_vec())
-            .collect_vec();
-        assert_eq!(heights, vec![vec![1, 2], vec![2, 0],]);
+        assert_eq!(forest.heights(), vec![vec![1, 2], vec![2, 0],]);
     }
 
     #[test]
@@ -258,6 +341,71 @@ mod tests {
         test!("33549" @ Line::Left(0) => 0 1 2 1 4);
     }
 
+    #[test]
+    fn test_parse_csv() {
+        let packed = parse("30373\n25512\n65332\n33549\n35390");
+        let csv = parse_csv(
+            "
+            3,0,3,7,3
+            2,5,5,1,2
+            6,5,3,3,2
+            3,3,5,4,9
+            3,5,3,9,0
+        ",
+        );
+        assert_eq!(csv.trees, packed.trees);
+        assert_eq!(compute(&csv), compute(&packed));
+        assert_eq!(compute_2(&csv), compute_2(&packed));
+
+        let tall = parse_csv("1,12,3\n10,0,255");
+        assert_eq!(tall.size(), (3, 2));
+        assert_eq!(compute(&tall), 6);
+        assert_eq!(solve("1,12,3\n10,0,255"), 6);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let forest = parse("30373\n25512\n65332\n33549\n35390");
+        let transposed = forest.transpose();
+        assert_eq!(transposed.transpose().trees, forest.trees);
+
+        let heights = |forest: &Forest, line| forest.trees(line).map(|t| t.height).collect_vec();
+        for i in 0..5 {
+            assert_eq!(
+                heights(&forest, Line::Up(i)),
+                heights(&transposed, Line::Left(i))
+            );
+            assert_eq!(
+                heights(&forest, Line::Down(i)),
+                heights(&transposed, Line::Right(i))
+            );
+        }
+        assert_eq!(compute(&transposed), compute(&forest));
+        assert_eq!(compute_2(&transposed), compute_2(&forest));
+
+        let wide = Forest::from_grid(vec![vec![1, 2, 3], vec![3, 0, 1]]);
+        assert_eq!(wide.transpose().size(), (2, 3));
+        assert_eq!(compute(&wide.transpose()), compute(&wide));
+    }
+
+    #[test]
+    fn test_solve_prefix() {
+        let input = "30373\n25512\n65332\n33549\n35390";
+        assert_eq!(solve_prefix(input), compute(&parse(input)));
+        assert_eq!(solve_prefix(""), 0);
+        assert_eq!(solve_prefix("5"), 1);
+
+        let mut rng = Rng::new(8);
+        for _ in 0..50 {
+            let (x_size, y_size) = (rng.range(1..12), rng.range(1..12));
+            let heights = (0..y_size)
+                .map(|_| (0..x_size).map(|_| rng.range(0..10) as u8).collect_vec())
+                .collect_vec();
+            let forest = Forest::from_grid(heights.clone());
+            assert_eq!(count_visible(&heights), compute(&forest), "{heights:?}");
+        }
+    }
+
     #[test]
     fn test_solve() {
         let input = "
@@ -1,77 +1,13 @@
 use std::{
     collections::HashSet,
-    iter::{once, repeat},
-    ops::{Add, Mul, Sub},
+    iter::{once, repeat, repeat_n},
 };
 
 use itertools::Itertools;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-struct Vector {
-    x: isize,
-    y: isize,
-}
-
-impl Sub for Vector {
-    type Output = Vector;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self::Output {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
-    }
-}
-
-impl Add for Vector {
-    type Output = Self;
-
-    fn add(self, rhs: Vector) -> Self::Output {
-        Self::Output {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
-    }
-}
+use crate::utils::{clean_lines, Direction, Vec2, Visualize};
 
-impl Mul for Vector {
-    type Output = Self;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        Self::Output {
-            x: self.x * rhs.x,
-            y: self.y * rhs.y,
-        }
-    }
-}
-
-impl Vector {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
-    }
-
-    fn dot(self, rhs: Self) -> isize {
-        let product = self * rhs;
-        product.x + product.y
-    }
-
-    fn funky_norm(self) -> Self {
-        let (x, y) = [self.x, self.y]
-            .into_iter()
-            .map(|p| {
-                if p == 0 {
-                    0
-                } else {
-                    let sign = if p < 0 { -1 } else { 1 };
-                    let abs = p.abs();
-                    sign * (abs + abs - 1) / abs
-                }
-            })
-            .collect_tuple()
-            .unwrap();
-        Self::new(x, y)
-    }
-}
+type Vector = Vec2<isize>;
 
 struct Snake<const N: usize> {
     head: Vector,
@@ -80,9 +16,8 @@ struct Snake<const N: usize> {
 
 trait SnakeLike {
     fn new() -> Self;
-    fn move_one(&mut self, direction: Direction);
+    fn move_one(&mut self, step: Vector);
     fn end(&self) -> Vector;
-    fn to_string(&self) -> String;
 }
 
 impl<const N: usize> SnakeLike for Snake<N> {
@@ -94,13 +29,13 @@ impl<const N: usize> SnakeLike for Snake<N> {
         }
     }
 
-    fn move_one(&mut self, direction: Direction) {
-        self.head = self.head + direction.into();
+    fn move_one(&mut self, step: Vector) {
+        self.head += step;
         let mut prev = self.head;
         for next in &mut self.tail {
             let difference = prev - *next;
-            if difference.dot(difference) > 2 {
-                *next = *next + difference.funky_norm();
+            if difference.chebyshev() > 1 {
+                *next += difference.signum();
             }
             prev = *next;
         }
@@ -109,7 +44,10 @@ impl<const N: usize> SnakeLike for Snake<N> {
     fn end(&self) -> Vector {
         self.tail[N - 1]
     }
-    fn to_string(&self) -> String {
+}
+
+impl<const N: usize> Visualize for Snake<N> {
+    fn visualize(&self) -> String {
         // Find the bounds of the snake
         let (min, max) = self
             .tail
@@ -145,55 +83,48 @@ impl<const N: usize> SnakeLike for Snake<N> {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
-impl From<Direction> for Vector {
-    fn from(direction: Direction) -> Self {
-        match direction {
-            Direction::Left => Vector::new(-1, 0),
-            Direction::Right => Vector::new(1, 0),
-            Direction::Down => Vector::new(0, -1),
-            Direction::Up => Vector::new(0, 1),
-        }
-    }
-}
-
-impl From<&str> for Direction {
-    fn from(s: &str) -> Self {
-        match s {
-            "U" => Direction::Up,
-            "D" => Direction::Down,
-            "L" => Direction::Left,
-            "R" => Direction::Right,
-            _ => panic!("Can't parse {s} into Direction"),
-        }
+fn parse_direction(s: &str) -> Direction {
+    match s {
+        "U" => Direction::Up,
+        "D" => Direction::Down,
+        "L" => Direction::Left,
+        "R" => Direction::Right,
+        _ => panic!("Can't parse {s} into Direction"),
     }
 }
 
 fn parse(input: &str) -> impl Iterator<Item = Direction> + '_ {
-    input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+    clean_lines(input)
         .flat_map(|l| {
             l.split(" ")
                 .tuples()
-                .flat_map(|(dir, num)| repeat(dir.into()).take(num.parse::<usize>().unwrap()))
+                .flat_map(|(dir, num)| repeat(parse_direction(dir)).take(num.parse::<usize>().unwrap()))
         })
 }
 
-fn compute<S: SnakeLike>(input: &str) -> usize {
+// Extended moves: `UL`, `UR`, `DL` and `DR` step diagonally, alongside the four cardinals
+fn parse_ext(input: &str) -> Vec<Vector> {
+    clean_lines(input)
+        .flat_map(|l| {
+            let (dir, num) = l.split_once(' ').unwrap();
+            let step = match dir.split_at(1) {
+                (d, "") => parse_direction(d).into(),
+                (v @ ("U" | "D"), h @ ("L" | "R")) => {
+                    Vector::from(parse_direction(v)) + parse_direction(h).into()
+                }
+                _ => panic!("Can't parse {dir} into a step"),
+            };
+            repeat_n(step, num.parse::<usize>().unwrap())
+        })
+        .collect()
+}
+
+fn compute<S: SnakeLike>(steps: impl Iterator<Item = Vector>) -> usize {
     let snake = S::new();
     let hs: HashSet<_> = [snake.end()].into();
-    parse(input)
-        .fold((hs, snake), |(mut hs, mut snake), d| {
-            snake.move_one(d);
+    steps
+        .fold((hs, snake), |(mut hs, mut snake), step| {
+            snake.move_one(step);
             hs.insert(snake.end());
             (hs, snake)
         })
@@ -201,12 +132,52 @@ fn compute<S: SnakeLike>(input: &str) -> usize {
         .len()
 }
 
+// Every knot stays within the bounds of the head's path, which includes the
+// origin, so the visited cells fit in a dense grid over those bounds. That skips
+// the hashing in `compute`, but takes memory for the whole bounding box rather
+// than just the visited cells, so a few long moves can make it huge.
+#[cfg(test)]
+fn compute_bounded<S: SnakeLike>(input: &str) -> usize {
+    let steps = parse(input).map(Vector::from).collect_vec();
+    let origin = Vector::new(0, 0);
+    let (min, max, _) = steps
+        .iter()
+        .fold((origin, origin, origin), |(min, max, head), &step| {
+            let head = head + step;
+            let min = Vector::new(min.x.min(head.x), min.y.min(head.y));
+            let max = Vector::new(max.x.max(head.x), max.y.max(head.y));
+            (min, max, head)
+        });
+    let width = (max.x - min.x + 1) as usize;
+    let height = (max.y - min.y + 1) as usize;
+    let mut visited = vec![false; width * height];
+    let mut snake = S::new();
+    let mut mark = |end: Vector| {
+        let offset = end - min;
+        visited[offset.y as usize * width + offset.x as usize] = true;
+    };
+    mark(snake.end());
+    for step in steps {
+        snake.move_one(step);
+        mark(snake.end());
+    }
+    visited.into_iter().filter(|&cell| cell).count()
+}
+
+pub(crate) fn visualize(input: &str) -> String {
+    let mut snake = Snake::<9>::new();
+    for d in parse(input) {
+        snake.move_one(d.into());
+    }
+    snake.visualize()
+}
+
 pub(crate) fn solve(input: &str) -> usize {
-    compute::<Snake<1>>(input)
+    compute::<Snake<1>>(parse_ext(input).into_iter())
 }
 
 pub(crate) fn solve_2(input: &str) -> usize {
-    compute::<Snake<9>>(input)
+    compute::<Snake<9>>(parse_ext(input).into_iter())
 }
 
 #[cfg(test)]
@@ -214,35 +185,35 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_funky_norm() {
-        assert_eq!(Vector::new(1, 0).funky_norm(), Vector::new(1, 0));
-        assert_eq!(Vector::new(2, 0).funky_norm(), Vector::new(1, 0));
-        assert_eq!(Vector::new(-1, 0).funky_norm(), Vector::new(-1, 0));
-        assert_eq!(Vector::new(-2, 0).funky_norm(), Vector::new(-1, 0));
+    fn test_signum() {
+        assert_eq!(Vector::new(1, 0).signum(), Vector::new(1, 0));
+        assert_eq!(Vector::new(2, 0).signum(), Vector::new(1, 0));
+        assert_eq!(Vector::new(-1, 0).signum(), Vector::new(-1, 0));
+        assert_eq!(Vector::new(-2, 0).signum(), Vector::new(-1, 0));
     }
 
     #[test]
     fn test_snake() {
         let mut snake = Snake::<1>::new();
-        snake.move_one(Direction::Down);
+        snake.move_one(Direction::Down.into());
         assert_eq!(
             (snake.head, snake.tail),
-            (Vector::new(0, -1), [Vector::new(0, 0)])
+            (Vector::new(0, 1), [Vector::new(0, 0)])
         );
-        snake.move_one(Direction::Down);
+        snake.move_one(Direction::Down.into());
         assert_eq!(
             (snake.head, snake.tail),
-            (Vector::new(0, -2), [Vector::new(0, -1)])
+            (Vector::new(0, 2), [Vector::new(0, 1)])
         );
-        snake.move_one(Direction::Right);
+        snake.move_one(Direction::Right.into());
         assert_eq!(
             (snake.head, snake.tail),
-            (Vector::new(1, -2), [Vector::new(0, -1)])
+            (Vector::new(1, 2), [Vector::new(0, 1)])
         );
-        snake.move_one(Direction::Right);
+        snake.move_one(Direction::Right.into());
         assert_eq!(
             (snake.head, snake.tail),
-            (Vector::new(2, -2), [Vector::new(1, -2)])
+            (Vector::new(2, 2), [Vector::new(1, 2)])
         );
     }
 
@@ -251,13 +222,13 @@ mod tests {
         let mut snake = Snake::<2>::new();
         // x...
 
-        snake.move_one(Direction::Right);
+        snake.move_one(Direction::Right.into());
         // xx..
 
-        snake.move_one(Direction::Right);
+        snake.move_one(Direction::Right.into());
         // xxx.
 
-        snake.move_one(Direction::Right);
+        snake.move_one(Direction::Right.into());
         // .xxx
 
         assert_eq!(
@@ -291,6 +262,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_ext() {
+        let steps = parse_ext(
+            "
+            UR 2
+            DL 1
+            L 1
+        ",
+        );
+        assert_eq!(
+            steps,
+            vec![
+                Vector::new(1, -1),
+                Vector::new(1, -1),
+                Vector::new(-1, 1),
+                Vector::new(-1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagonal_moves() {
+        let mut snake = Snake::<1>::new();
+        snake.move_one(Vector::new(1, 1));
+        assert_eq!(
+            (snake.head, snake.tail),
+            (Vector::new(1, 1), [Vector::new(0, 0)])
+        );
+        snake.move_one(Vector::new(1, 1));
+        assert_eq!(
+            (snake.head, snake.tail),
+            (Vector::new(2, 2), [Vector::new(1, 1)])
+        );
+        snake.move_one(Vector::new(1, -1));
+        assert_eq!(
+            (snake.head, snake.tail),
+            (Vector::new(3, 1), [Vector::new(2, 1)])
+        );
+        snake.move_one(Vector::new(1, -1));
+        assert_eq!(
+            (snake.head, snake.tail),
+            (Vector::new(4, 0), [Vector::new(3, 0)])
+        );
+
+        let steps = parse_ext("UR 3\nDR 3\nUL 1");
+        assert_eq!(compute::<Snake<1>>(steps.into_iter()), 6);
+    }
+
     #[test]
     fn test_solve() {
         let input = "
@@ -321,6 +340,24 @@ mod tests {
         assert_eq!(solve_2(input), 36);
     }
 
+    #[test]
+    fn test_compute_bounded() {
+        let input = "
+            R 5
+            U 8
+            L 8
+            D 3
+            R 17
+            D 10
+            L 25
+            U 20
+        ";
+        assert_eq!(compute_bounded::<Snake<1>>(input), solve(input));
+        assert_eq!(compute_bounded::<Snake<9>>(input), solve_2(input));
+        assert_eq!(compute_bounded::<Snake<9>>(input), 36);
+        assert_eq!(compute_bounded::<Snake<1>>(""), 1);
+    }
+
     #[test]
     fn test_print() {
         let input = "
@@ -335,8 +372,8 @@ mod tests {
         ";
         let mut snake = Snake::<9>::new();
         for direction in parse(input) {
-            snake.move_one(direction);
-            println!("{}", snake.to_string());
+            snake.move_one(direction.into());
+            println!("{}", snake.visualize());
         }
     }
 }
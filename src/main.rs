@@ -1,14 +1,63 @@
-use clap::Parser;
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use utils::{
+    fetch::{fetch_input, Fetched},
+    MemoStats, ParseError, Rng,
+};
 
 mod utils;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
     #[arg(value_enum, default_value_t=Task::Latest)]
     task: Task,
+
+    /// Run every registered part of a range of days, e.g. `5..=10` or `5..11`
+    #[arg(long, value_parser = parse_days, conflicts_with = "task")]
+    days: Option<RangeInclusive<u8>>,
+
+    /// Read inputs from one file with `--- day N ---` section headers
+    #[arg(long)]
+    bundle: Option<PathBuf>,
+
+    /// TOML file of known answers to compare against, e.g. `1 = [67016, 200116]`
+    #[arg(long)]
+    check: Option<PathBuf>,
+
+    /// Print the day's final state instead of its answer, where supported
+    #[arg(long)]
+    visualize: bool,
+
+    /// Print a breakdown of the day's answer instead of the answer, where supported
+    #[arg(long)]
+    report: bool,
+
+    /// Only run the day's parser and report how many items it produced
+    #[arg(long)]
+    parse_only: bool,
+
+    /// Report memo table size and hit rate for the dynamic programming days
+    #[arg(long)]
+    stats: bool,
+
+    /// Solve an input generated from this seed, failing if the solver panics
+    #[arg(long, hide = true)]
+    fuzz: Option<u64>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Download a day's input into inputs/ using the AOC_SESSION cookie
+    Fetch { day: u8 },
 }
 
+// `N+` registers both parts of day N, a bare `N` registers only part 1
 utils::make_runner!(
     1+,
     2+,
@@ -34,9 +83,185 @@ utils::make_runner!(
     22+,
     23+,
     24+,
-    25,
+    25+,
 );
 
+fn parse_days(s: &str) -> Result<RangeInclusive<u8>, String> {
+    let parse_day = |d: &str| {
+        d.trim()
+            .parse::<u8>()
+            .map_err(|e| format!("invalid day {d:?}: {e}"))
+    };
+    let (start, end) = if let Some((start, end)) = s.split_once("..=") {
+        (parse_day(start)?, parse_day(end)?)
+    } else if let Some((start, end)) = s.split_once("..") {
+        let end = parse_day(end)?
+            .checked_sub(1)
+            .ok_or_else(|| format!("empty range {s:?}"))?;
+        (parse_day(start)?, end)
+    } else {
+        let day = parse_day(s)?;
+        (day, day)
+    };
+    if start > end {
+        return Err(format!("empty range {s:?}"));
+    }
+    if let Some(day) = (start..=end).find(|&day| tasks_for_days(&(day..=day)).is_empty()) {
+        return Err(format!("day {day} is not registered"));
+    }
+    Ok(start..=end)
+}
+
+fn visualize(day: u8, input: &str) -> Option<String> {
+    match day {
+        9 => Some(day9::visualize(input)),
+        10 => Some(day10::visualize(input)),
+        11 => Some(day11::visualize(input)),
+        14 => Some(day14::visualize(input)),
+        16 => Some(day16::visualize(input)),
+        18 => Some(day18::visualize(input)),
+        23 => Some(day23::visualize(input)),
+        24 => Some(day24::visualize(input)),
+        _ => None,
+    }
+}
+
+fn report(day: u8, input: &str) -> Option<String> {
+    match day {
+        6 => Some(day6::report(input)),
+        7 => Some(day7::report(input)),
+        10 => Some(day10::report(input)),
+        11 => Some(day11::report(input)),
+        14 => Some(day14::report(input)),
+        16 => Some(day16::report(input)),
+        17 => Some(day17::report(input)),
+        22 => Some(day22::report(input)),
+        _ => None,
+    }
+}
+
+fn parse_count(day: u8, input: &str) -> Option<Result<usize, ParseError>> {
+    match day {
+        13 => Some(day13::parse_count(input)),
+        15 => Some(day15::parse_count(input)),
+        16 => Some(day16::parse_count(input)),
+        19 => Some(day19::parse_count(input)),
+        21 => Some(day21::parse_count(input)),
+        _ => None,
+    }
+}
+
+fn generate(day: u8, rng: &mut Rng) -> Option<String> {
+    match day {
+        5 => Some(day5::generate(rng)),
+        _ => None,
+    }
+}
+
+fn solve_with_stats(day: u8, part: u8, input: &str) -> Option<(String, MemoStats)> {
+    let result = match (day, part) {
+        (16, 1) => day16::solve_with_stats(input),
+        (16, 2) => day16::solve_2_with_stats(input),
+        (19, 1) => day19::solve_with_stats(input),
+        (19, 2) => day19::solve_2_with_stats(input),
+        _ => return None,
+    };
+    // Without stats the plain solver runs instead, and reports the parse error
+    let (answer, stats) = result.ok()?;
+    Some((answer.to_string(), stats))
+}
+
 fn main() {
-    run(Args::parse());
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Fetch { day }) => match fetch_input(day) {
+            Ok(Fetched::Downloaded(path)) => println!("Downloaded {}", path.display()),
+            Ok(Fetched::AlreadyExists(path)) => {
+                println!("Skipping {}: already exists", path.display())
+            }
+            Err(e) => {
+                eprintln!("Couldn't fetch day {day}: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => run(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_days() {
+        for day in 1..=25 {
+            for task in [format!("day{day}"), format!("day{day}-2")] {
+                assert!(Args::try_parse_from(["aoc", &task]).is_ok(), "{task}");
+            }
+        }
+        assert!(Args::try_parse_from(["aoc", "day26"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("5..=10"), Ok(5..=10));
+        assert_eq!(parse_days("5..11"), Ok(5..=10));
+        assert_eq!(parse_days("7"), Ok(7..=7));
+        assert!(parse_days("10..=5").is_err());
+        assert!(parse_days("5..5").is_err());
+        assert!(parse_days("0..=3").is_err());
+        assert!(parse_days("20..=26").is_err());
+        assert!(parse_days("a..=3").is_err());
+        let days = tasks_for_days(&(24..=25))
+            .into_iter()
+            .map(|task| task_info(task).0)
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![24, 24, 25, 25]);
+        assert!(Args::try_parse_from(["aoc", "--days", "1..=3"]).is_ok());
+        assert!(Args::try_parse_from(["aoc", "day1", "--days", "1..=3"]).is_err());
+    }
+
+    #[test]
+    fn test_visualize() {
+        assert!(visualize(1, "").is_none());
+        assert!(visualize(14, "500,1 -> 500,1").is_some());
+    }
+
+    #[test]
+    fn test_report() {
+        assert!(report(1, "").is_none());
+        assert!(report(7, "$ cd /\n$ ls\n10 a").is_some());
+        assert!(Args::try_parse_from(["aoc", "day7", "--report"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert!(parse_count(1, "").is_none());
+        assert_eq!(
+            parse_count(21, "root: humn + abcd\nhumn: 5\nabcd: 3\n"),
+            Some(Ok(3))
+        );
+        assert!(matches!(parse_count(15, "Sensor at x=2"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_generate() {
+        assert!(generate(1, &mut Rng::new(0)).is_none());
+        assert!(generate(5, &mut Rng::new(0)).is_some());
+        assert!(Args::try_parse_from(["aoc", "day5", "--fuzz", "42"]).is_ok());
+    }
+
+    #[test]
+    fn test_every_day_module_is_registered() {
+        let src = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+        let modules = src
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter_map(|name| Some(name.strip_suffix(".rs")?.strip_prefix("day")?.to_owned()))
+            .collect::<Vec<_>>();
+        assert_eq!(modules.len(), 25);
+        for day in modules {
+            let task = format!("day{day}");
+            assert!(Args::try_parse_from(["aoc", &task]).is_ok(), "{task}");
+        }
+    }
 }
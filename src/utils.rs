@@ -1,3 +1,564 @@
+pub(crate) mod check;
+pub(crate) mod fetch;
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::{self, Display},
+    hash::Hash,
+    num::ParseIntError,
+    ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Range, Sub},
+};
+
+pub(crate) trait Visualize {
+    fn visualize(&self) -> String;
+}
+
+pub(crate) trait Answer {
+    fn into_answer(self) -> String;
+}
+
+macro_rules! impl_answer {
+    ($($t:ty),*) => {
+        $(impl Answer for $t {
+            fn into_answer(self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+impl_answer!(i32, i64, isize, usize, String);
+
+impl<T: Answer, E: Display> Answer for Result<T, E> {
+    fn into_answer(self) -> String {
+        match self {
+            Ok(answer) => answer.into_answer(),
+            Err(e) => format!("error: {e}"),
+        }
+    }
+}
+
+// Trimmed, non-blank lines, skipping `#` comments
+pub(crate) fn clean_lines(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+}
+
+// Every integer in the line, in order. A `-` directly before digits is a sign
+// unless it follows another digit, so ranges like `2-4` give 2 and 4. Anything
+// between the numbers is skipped, but a number too big for an isize is an error.
+pub(crate) fn parse_ints(line: &str) -> Result<Vec<isize>, ParseIntError> {
+    let bytes = line.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let negative = bytes[i] == b'-'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)
+            && (i == 0 || !bytes[i - 1].is_ascii_digit());
+        let start = if negative { i + 1 } else { i };
+        if !bytes[start].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let digits = bytes[start..].iter().take_while(|b| b.is_ascii_digit());
+        let end = start + digits.count();
+        // Starting from the sign, if any, so isize::MIN still fits
+        result.push(line[i..end].parse()?);
+        i = end;
+    }
+    Ok(result)
+}
+
+// A line that doesn't match the format its day expects
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ParseError {
+    pub(crate) line: String,
+    pub(crate) expected: &'static str,
+}
+
+impl ParseError {
+    pub(crate) fn new(line: &str, expected: &'static str) -> Self {
+        Self {
+            line: line.into(),
+            expected,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "couldn't parse {:?}, expected {}", self.line, self.expected)
+    }
+}
+
+// Splits a bundle of inputs separated by `--- day N ---` headers. Sections
+// with malformed or repeated headers are skipped with a warning.
+pub(crate) fn split_bundle(input: &str) -> HashMap<u8, &str> {
+    let mut sections = HashMap::new();
+    let mut add_section = |day: u8, section| {
+        if sections.insert(day, section).is_some() {
+            eprintln!("Skipping earlier bundle section for day {day}: it appears twice");
+        }
+    };
+    let mut current = None;
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        let header = line.trim();
+        if header.len() > 6 && header.starts_with("---") && header.ends_with("---") {
+            if let Some((day, start)) = current.take() {
+                add_section(day, &input[start..offset]);
+            }
+            let day = header
+                .trim_matches('-')
+                .trim()
+                .strip_prefix("day ")
+                .and_then(|d| d.trim().parse::<u8>().ok());
+            match day {
+                Some(day) => current = Some((day, offset + line.len())),
+                None => eprintln!("Skipping bundle section with malformed header {header:?}"),
+            }
+        }
+        offset += line.len();
+    }
+    if let Some((day, start)) = current {
+        add_section(day, &input[start..]);
+    }
+    sections
+}
+
+pub(crate) trait DpStats {
+    fn dp_stats(&self) -> MemoStats;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MemoStats {
+    pub(crate) entries: usize,
+    pub(crate) lookups: usize,
+    pub(crate) hits: usize,
+}
+
+impl MemoStats {
+    pub(crate) fn hit_rate(&self) -> f64 {
+        match self.lookups {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl AddAssign for MemoStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.entries += rhs.entries;
+        self.lookups += rhs.lookups;
+        self.hits += rhs.hits;
+    }
+}
+
+// Memo table for recursive searches: `compute` gets the table back so it can recurse
+pub(crate) struct MemoTable<K, V> {
+    table: HashMap<K, V>,
+    stats: MemoStats,
+}
+
+impl<K, V> Default for MemoTable<K, V> {
+    fn default() -> Self {
+        Self {
+            table: HashMap::new(),
+            stats: MemoStats::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> MemoTable<K, V> {
+    pub(crate) fn get_or_compute(&mut self, key: K, compute: impl FnOnce(&mut Self) -> V) -> V {
+        self.stats.lookups += 1;
+        if let Some(value) = self.table.get(&key) {
+            self.stats.hits += 1;
+            return value.clone();
+        }
+        let value = compute(self);
+        self.table.insert(key, value.clone());
+        self.stats.entries = self.table.len();
+        value
+    }
+}
+
+impl<K, V> DpStats for MemoTable<K, V> {
+    fn dp_stats(&self) -> MemoStats {
+        self.stats
+    }
+}
+
+impl Display for MemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} memo entries, {}/{} lookups hit ({:.1}%)",
+            self.entries,
+            self.hits,
+            self.lookups,
+            100.0 * self.hit_rate()
+        )
+    }
+}
+
+// Screen orientation: `Up` is towards smaller y
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub(crate) fn cw(self) -> Self {
+        self.rotate(Vec2::rotate_cw)
+    }
+
+    pub(crate) fn ccw(self) -> Self {
+        self.rotate(Vec2::rotate_ccw)
+    }
+
+    fn rotate(self, rotate: fn(Vec2<isize>) -> Vec2<isize>) -> Self {
+        let Vec2 { x, y } = rotate(self.into());
+        Self::from_vector((x, y))
+    }
+
+    pub(crate) fn to_vector(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+        }
+    }
+
+    pub(crate) fn from_vector(vector: (isize, isize)) -> Self {
+        match vector {
+            (0, -1) => Direction::Up,
+            (1, 0) => Direction::Right,
+            (0, 1) => Direction::Down,
+            (-1, 0) => Direction::Left,
+            _ => panic!("{vector:?} isn't a unit vector"),
+        }
+    }
+}
+
+impl From<Direction> for Vec2<isize> {
+    fn from(direction: Direction) -> Self {
+        let (x, y) = direction.to_vector();
+        Self::new(x, y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Grid<T> {
+    cells: Vec<T>,
+    size: (usize, usize),
+}
+
+impl<T> Grid<T> {
+    pub(crate) fn new(rows: Vec<Vec<T>>) -> Self {
+        let size = (rows.first().map_or(0, |row| row.len()), rows.len());
+        assert!(
+            rows.iter().all(|row| row.len() == size.0),
+            "Grid isn't rectangular"
+        );
+        Self {
+            cells: rows.into_iter().flatten().collect(),
+            size,
+        }
+    }
+
+    pub(crate) fn from_chars(input: &str, mut f: impl FnMut((usize, usize), char) -> T) -> Self {
+        Self::new(
+            input
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .enumerate()
+                .map(|(y, l)| l.chars().enumerate().map(|(x, c)| f((x, y), c)).collect())
+                .collect(),
+        )
+    }
+
+    pub(crate) fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    pub(crate) fn in_bounds(&self, (x, y): (usize, usize)) -> bool {
+        x < self.size.0 && y < self.size.1
+    }
+
+    pub(crate) fn get(&self, pos: (usize, usize)) -> Option<&T> {
+        self.in_bounds(pos).then(|| &self[pos])
+    }
+
+    pub(crate) fn neighbors4(&self, pos: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        self.offsets(pos, [(0, 1), (0, -1), (1, 0), (-1, 0)])
+    }
+
+    // Every grid day so far only moves orthogonally
+    #[cfg(test)]
+    pub(crate) fn neighbors8(&self, pos: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        #[rustfmt::skip]
+        let offsets = [
+            (-1, -1), (0, -1), (1, -1),
+            (-1, 0),           (1, 0),
+            (-1, 1),  (0, 1),  (1, 1),
+        ];
+        self.offsets(pos, offsets)
+    }
+
+    fn offsets<const N: usize>(
+        &self,
+        (x, y): (usize, usize),
+        offsets: [(isize, isize); N],
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let size = self.size;
+        offsets
+            .into_iter()
+            .map(move |(dx, dy)| (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy)))
+            .filter(move |&(x, y)| x < size.0 && y < size.1)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        assert!(self.in_bounds((x, y)), "({x}, {y}) is out of bounds");
+        &self.cells[y * self.size.0 + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        assert!(self.in_bounds((x, y)), "({x}, {y}) is out of bounds");
+        &mut self.cells[y * self.size.0 + x]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub(crate) struct Vec2<T> {
+    pub(crate) x: T,
+    pub(crate) y: T,
+}
+
+impl<T> Vec2<T> {
+    pub(crate) const fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Vec2<isize> {
+    pub(crate) fn dot(self, rhs: Self) -> isize {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    pub(crate) fn manhattan(self) -> isize {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub(crate) fn chebyshev(self) -> isize {
+        self.x.abs().max(self.y.abs())
+    }
+
+    pub(crate) fn signum(self) -> Self {
+        Self::new(self.x.signum(), self.y.signum())
+    }
+
+    // Quarter turns, named for screen orientation where y grows downwards
+    pub(crate) fn rotate_cw(self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
+    pub(crate) fn rotate_ccw(self) -> Self {
+        Self::new(self.y, -self.x)
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vec2<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Vec2<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vec2<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Vec2<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vec2<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+pub(crate) fn dijkstra<N, FN, FR, I>(
+    start: N,
+    neighbors: FN,
+    is_goal: FR,
+) -> Option<(usize, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    FN: FnMut(&N) -> I,
+    FR: FnMut(&N) -> bool,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    astar(start, neighbors, |_| 0, is_goal)
+}
+
+pub(crate) fn astar<N, FN, FH, FR, I>(
+    start: N,
+    mut neighbors: FN,
+    heuristic: FH,
+    mut is_goal: FR,
+) -> Option<(usize, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    FN: FnMut(&N) -> I,
+    FH: Fn(&N) -> usize,
+    FR: FnMut(&N) -> bool,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    // Nodes are stored alongside the index of their parent, so the heap only
+    // needs to order indexes and N doesn't have to be Ord
+    let mut nodes = vec![(start.clone(), None)];
+    let mut costs = HashMap::from([(start.clone(), 0)]);
+    let mut queue = BinaryHeap::from([Reverse((heuristic(&start), 0, 0))]);
+    while let Some(Reverse((_, cost, index))) = queue.pop() {
+        let node = nodes[index].0.clone();
+        if costs[&node] < cost {
+            continue;
+        }
+        if is_goal(&node) {
+            let mut path = Vec::new();
+            let mut current = Some(index);
+            while let Some(i) = current {
+                path.push(nodes[i].0.clone());
+                current = nodes[i].1;
+            }
+            path.reverse();
+            return Some((cost, path));
+        }
+        for (next, step_cost) in neighbors(&node) {
+            let next_cost = cost + step_cost;
+            if costs.get(&next).is_some_and(|&c| c <= next_cost) {
+                continue;
+            }
+            costs.insert(next.clone(), next_cost);
+            queue.push(Reverse((
+                next_cost + heuristic(&next),
+                next_cost,
+                nodes.len(),
+            )));
+            nodes.push((next, Some(index)));
+        }
+    }
+    None
+}
+
+// A repeat found by `detect_cycle`: the key first seen at step `offset` came back
+// `length` steps later, and the value grew by `gain` in between
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct CycleInfo {
+    pub(crate) offset: usize,
+    pub(crate) length: usize,
+    pub(crate) gain: usize,
+}
+
+impl CycleInfo {
+    // How many times `step` ran before the cycle was found
+    #[cfg(test)]
+    pub(crate) fn steps_taken(&self) -> usize {
+        self.offset + self.length + 1
+    }
+
+    // Splits `remaining` steps into the gain of the whole cycles they contain, and
+    // the leftover steps that still have to be run
+    pub(crate) fn skip(&self, remaining: usize) -> (usize, usize) {
+        (
+            (remaining / self.length) * self.gain,
+            remaining % self.length,
+        )
+    }
+}
+
+// Runs `step` until a key repeats, or `max_steps` times. `step` returns a key
+// describing the state and a running total; steps that can't be keyed return `None`.
+pub(crate) fn detect_cycle<K: Ord>(
+    max_steps: usize,
+    mut step: impl FnMut() -> Option<(K, usize)>,
+) -> Option<CycleInfo> {
+    let mut seen = BTreeMap::new();
+    for i in 0..max_steps {
+        let Some((key, value)) = step() else {
+            continue;
+        };
+        if let Some(&(offset, prev_value)) = seen.get(&key) {
+            return Some(CycleInfo {
+                offset,
+                length: i - offset,
+                gain: value - prev_value,
+            });
+        }
+        seen.insert(key, (i, value));
+    }
+    None
+}
+
+// SplitMix64: small and seedable, which is all that generating inputs needs
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // The modulo bias is negligible for the small ranges used here
+    pub(crate) fn range(&mut self, range: Range<usize>) -> usize {
+        assert!(!range.is_empty(), "empty range {range:?}");
+        range.start + (self.next_u64() % (range.end - range.start) as u64) as usize
+    }
+}
+
 macro_rules! make_runner {
     (@helper
         { $($mods:tt)* }
@@ -16,10 +577,12 @@ macro_rules! make_runner {
                 $($labels)*
             }
             {
-                Task::[< Day $day >] => {
-                    let input = include_str!(concat!("../inputs/", $day, ".txt"));
-                    (stringify!($day), [< day $day >]::solve(input).to_string())
-                },
+                Task::[< Day $day >] => (
+                    $day,
+                    1,
+                    include_str!(concat!("../inputs/", $day, ".txt")),
+                    |input| crate::utils::Answer::into_answer([< day $day >]::solve(input)),
+                ),
                 $($arms)*
             }
             $($rest)*
@@ -43,14 +606,18 @@ macro_rules! make_runner {
                 $($labels)*
             }
             {
-                Task::[< Day $day _2 >] => {
-                    let input = include_str!(concat!("../inputs/", $day, ".txt"));
-                    (concat!($day, " (part 2)"), [< day $day >]::solve_2(input).to_string())
-                },
-                Task::[< Day $day >] => {
-                    let input = include_str!(concat!("../inputs/", $day, ".txt"));
-                    (stringify!($day), [< day $day >]::solve(input).to_string())
-                },
+                Task::[< Day $day _2 >] => (
+                    $day,
+                    2,
+                    include_str!(concat!("../inputs/", $day, ".txt")),
+                    |input| crate::utils::Answer::into_answer([< day $day >]::solve_2(input)),
+                ),
+                Task::[< Day $day >] => (
+                    $day,
+                    1,
+                    include_str!(concat!("../inputs/", $day, ".txt")),
+                    |input| crate::utils::Answer::into_answer([< day $day >]::solve(input)),
+                ),
                 $($arms)*
             }
             $($rest)*
@@ -66,15 +633,130 @@ macro_rules! make_runner {
 
         $($mods)*
 
-        fn run(args: Args) {
-            let start = std::time::Instant::now();
-            let (day, result) = match args.task {
+        fn task_info(task: Task) -> (u8, u8, &'static str, fn(&str) -> String) {
+            match task {
                 $lhs => $rhs,
                 Task::Latest => $rhs,
                 $($rest_lhs => $rest_rhs,)*
+            }
+        }
+
+        // Registered tasks for the given days, in ascending (day, part) order
+        fn tasks_for_days(days: &std::ops::RangeInclusive<u8>) -> Vec<Task> {
+            let mut tasks = <Task as clap::ValueEnum>::value_variants()
+                .iter()
+                .copied()
+                .filter(|&task| !matches!(task, Task::Latest) && days.contains(&task_info(task).0))
+                .collect::<Vec<_>>();
+            tasks.sort_by_key(|&task| {
+                let (day, part, _, _) = task_info(task);
+                (day, part)
+            });
+            tasks
+        }
+
+        fn run(args: Args) {
+            let tasks = match &args.days {
+                Some(days) => tasks_for_days(days),
+                None => vec![args.task],
+            };
+            let bundle = args.bundle.as_ref().map(|path| {
+                std::fs::read_to_string(path).unwrap_or_else(|e| {
+                    eprintln!("Couldn't read bundle from {}: {e}", path.display());
+                    std::process::exit(1);
+                })
+            });
+            let sections = bundle.as_deref().map(crate::utils::split_bundle);
+            let answers = args.check.as_ref().map(|path| {
+                crate::utils::check::Answers::load(path).unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                })
+            });
+            // Every task runs, so a check reports on each part before failing
+            let mut passed = true;
+            for task in tasks {
+                passed &= run_task(&args, task, sections.as_ref(), answers.as_ref());
+            }
+            if !passed {
+                std::process::exit(1);
+            }
+        }
+
+        // False if the answer didn't match the one being checked against
+        fn run_task(
+            args: &Args,
+            task: Task,
+            sections: Option<&std::collections::HashMap<u8, &str>>,
+            answers: Option<&crate::utils::check::Answers>,
+        ) -> bool {
+            let (day, part, mut input, solver) = task_info(task);
+            if let Some(sections) = sections {
+                match sections.get(&day) {
+                    Some(section) => input = section,
+                    None => {
+                        eprintln!("Skipping day {day}: no section for it in the bundle");
+                        return true;
+                    }
+                }
+            }
+            if args.visualize {
+                match crate::visualize(day, input) {
+                    Some(rendered) => println!("{rendered}"),
+                    None => println!("No visualization available for day {day}"),
+                }
+                return true;
+            }
+            if args.report {
+                match crate::report(day, input) {
+                    Some(report) => println!("{report}"),
+                    None => println!("No report available for day {day}"),
+                }
+                return true;
+            }
+            if args.parse_only {
+                match crate::parse_count(day, input) {
+                    Some(Ok(count)) => println!("Parsed {count} items for day {day}"),
+                    Some(Err(e)) => println!("Couldn't parse day {day}: {e}"),
+                    None => println!("No parser dry-run available for day {day}"),
+                }
+                return true;
+            }
+            if let Some(seed) = args.fuzz {
+                let Some(input) = crate::generate(day, &mut crate::utils::Rng::new(seed)) else {
+                    println!("No input generator available for day {day}");
+                    return true;
+                };
+                match std::panic::catch_unwind(|| solver(&input)) {
+                    Ok(result) => println!("Fuzzed day {day} with seed {seed}: {result}"),
+                    Err(_) => {
+                        eprintln!("Day {day} panicked on the input from seed {seed}:\n{input}");
+                        std::process::exit(1);
+                    }
+                }
+                return true;
+            }
+            let start = std::time::Instant::now();
+            let (result, stats) = match args.stats.then(|| crate::solve_with_stats(day, part, input)).flatten() {
+                Some((result, stats)) => (result, Some(stats)),
+                None => (solver(input), None),
             };
             let duration = start.elapsed().as_secs_f32();
-            println!("Computed result for day {day} in {duration:.3} seconds: {result}");
+            let label = match part {
+                1 => day.to_string(),
+                _ => format!("{day} (part {part})"),
+            };
+            println!("Computed result for day {label} in {duration:.3} seconds: {result}");
+            if args.stats {
+                match stats {
+                    Some(stats) => println!("Memo stats: {stats}"),
+                    None => println!("No memo stats available for day {day}"),
+                }
+            }
+            match answers {
+                Some(answers) => crate::utils::check::check_answer(answers, day, part, &result),
+                None => true,
+            }
         }
     );
 
@@ -84,3 +766,287 @@ macro_rules! make_runner {
 }
 
 pub(crate) use make_runner;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_lines() {
+        let input = "
+            # a comment
+            first  
+
+            second # not a comment
+              #indented comment
+        ";
+        assert_eq!(
+            clean_lines(input).collect::<Vec<_>>(),
+            vec!["first", "second # not a comment"]
+        );
+    }
+
+    #[test]
+    fn test_parse_ints() {
+        let ints = |line: &str| parse_ints(line).unwrap();
+        assert_eq!(ints("2-4,6-8"), vec![2, 4, 6, 8]);
+        assert_eq!(ints("-1,0,-23"), vec![-1, 0, -23]);
+        assert_eq!(
+            ints("Sensor at x=2, y=-18: closest beacon is at x=-2, y=15"),
+            vec![2, -18, -2, 15]
+        );
+        assert_eq!(ints("a--5 - 3 x-"), vec![-5, 3]);
+        assert_eq!(ints("no numbers"), Vec::<isize>::new());
+        assert_eq!(ints(&isize::MIN.to_string()), vec![isize::MIN]);
+        assert!(parse_ints("1,99999999999999999999,3").is_err());
+    }
+
+    #[test]
+    fn test_direction_rotation() {
+        let all = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ];
+        for d in all {
+            assert_eq!(d.cw().cw().cw().cw(), d);
+            assert_eq!(d.cw().ccw(), d);
+            let ((x, y), (cw_x, cw_y)) = (d.to_vector(), d.cw().to_vector());
+            assert_eq!((cw_x, cw_y), (-y, x));
+            assert_eq!(Vec2::from(d.cw()), Vec2::from(d).rotate_cw());
+        }
+        assert_eq!(Vec2::from(Direction::Up), Vec2::new(0, -1));
+        assert_eq!(Direction::Up.cw(), Direction::Right);
+        assert_eq!(Direction::Up.ccw(), Direction::Left);
+    }
+
+    #[test]
+    fn test_vec2_rotation() {
+        let start = Vec2::new(2, -1);
+        let turns = |rotate: fn(Vec2<isize>) -> Vec2<isize>| {
+            std::iter::successors(Some(start), |&v| Some(rotate(v)))
+                .take(5)
+                .collect::<Vec<_>>()
+        };
+        let (cw, mut ccw) = (turns(Vec2::rotate_cw), turns(Vec2::rotate_ccw));
+        let expected = [(2, -1), (1, 2), (-2, 1), (-1, -2), (2, -1)];
+        assert_eq!(cw, expected.map(|(x, y)| Vec2::new(x, y)));
+        ccw.reverse();
+        assert_eq!(ccw, cw);
+        for d in [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ] {
+            let (x, y) = d.to_vector();
+            let Vec2 { x, y } = Vec2::new(x, y).rotate_cw();
+            assert_eq!(Direction::from_vector((x, y)), d.cw());
+            assert_eq!(Direction::from_vector(d.to_vector()), d);
+        }
+    }
+
+    #[test]
+    fn test_memo_table() {
+        fn fib(memo: &mut MemoTable<u64, u64>, n: u64) -> u64 {
+            if n < 2 {
+                return n;
+            }
+            memo.get_or_compute(n, |memo| fib(memo, n - 1) + fib(memo, n - 2))
+        }
+        let mut memo = MemoTable::default();
+        assert_eq!(fib(&mut memo, 90), 2880067194370816120);
+        let stats = memo.dp_stats();
+        assert_eq!(stats.entries, 89);
+        assert_eq!(stats.lookups - stats.hits, stats.entries);
+        assert_eq!(fib(&mut memo, 90), 2880067194370816120);
+        assert_eq!(memo.dp_stats().hits, stats.hits + 1);
+    }
+
+    #[test]
+    fn test_split_bundle() {
+        let bundle = "\
+--- day 1 ---
+1000
+2000
+
+3000
+--- day 2 ---
+A Y
+--- day two ---
+ignored
+";
+        let sections = split_bundle(bundle);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[&1], "1000\n2000\n\n3000\n");
+        assert_eq!(sections[&2], "A Y\n");
+    }
+
+    #[test]
+    fn test_memo_stats() {
+        let mut stats = MemoStats::default();
+        assert_eq!(stats.hit_rate(), 0.0);
+        stats += MemoStats {
+            entries: 3,
+            lookups: 4,
+            hits: 1,
+        };
+        assert_eq!(stats.hit_rate(), 0.25);
+        assert_eq!(stats.to_string(), "3 memo entries, 1/4 lookups hit (25.0%)");
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = Grid::from_chars(
+            "
+            abc
+            def
+            ",
+            |_, c| c,
+        );
+        assert_eq!(grid.size(), (3, 2));
+        assert_eq!(grid[(2, 1)], 'f');
+        assert_eq!(grid.get((1, 0)), Some(&'b'));
+        assert_eq!(grid.get((3, 0)), None);
+        assert_eq!(grid.get((0, 2)), None);
+        assert!(grid.in_bounds((2, 1)));
+        assert!(!grid.in_bounds((usize::MAX, 0)));
+    }
+
+    #[test]
+    fn test_grid_neighbors() {
+        let grid = Grid::new(vec![vec![0; 3]; 3]);
+        let sorted = |it: &mut dyn Iterator<Item = (usize, usize)>| {
+            let mut v = it.collect::<Vec<_>>();
+            v.sort();
+            v
+        };
+        assert_eq!(sorted(&mut grid.neighbors4((0, 0))), vec![(0, 1), (1, 0)]);
+        assert_eq!(sorted(&mut grid.neighbors4((2, 2))), vec![(1, 2), (2, 1)]);
+        assert_eq!(
+            sorted(&mut grid.neighbors4((1, 1))),
+            vec![(0, 1), (1, 0), (1, 2), (2, 1)]
+        );
+        assert_eq!(
+            sorted(&mut grid.neighbors8((0, 0))),
+            vec![(0, 1), (1, 0), (1, 1)]
+        );
+        assert_eq!(
+            sorted(&mut grid.neighbors8((2, 0))),
+            vec![(1, 0), (1, 1), (2, 1)]
+        );
+        assert_eq!(sorted(&mut grid.neighbors8((1, 1))).len(), 8);
+        assert_eq!(Grid::new(vec![vec![0]]).neighbors8((0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn test_vec2() {
+        let a = Vec2::new(3, -4);
+        let b = Vec2::new(-1, 2);
+        assert_eq!(a + b, Vec2::new(2, -2));
+        assert_eq!(a - b, Vec2::new(4, -6));
+        assert_eq!(a * 2, Vec2::new(6, -8));
+        assert_eq!(-a, Vec2::new(-3, 4));
+        assert_eq!(a.dot(b), -11);
+        assert_eq!(a.manhattan(), 7);
+        assert_eq!(a.chebyshev(), 4);
+        assert_eq!(a.signum(), Vec2::new(1, -1));
+        assert_eq!(Vec2::new(0, 5).signum(), Vec2::new(0, 1));
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+    }
+
+    //   1     1
+    // a --> b --> d
+    //  \         ^
+    //   \-> c --/
+    //    1     5
+    fn neighbors(node: &char) -> Vec<(char, usize)> {
+        match node {
+            'a' => vec![('b', 1), ('c', 1)],
+            'b' => vec![('d', 1)],
+            'c' => vec![('d', 5)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        assert_eq!(
+            dijkstra('a', neighbors, |&n| n == 'd'),
+            Some((2, vec!['a', 'b', 'd']))
+        );
+        assert_eq!(
+            dijkstra('a', neighbors, |&n| n == 'a'),
+            Some((0, vec!['a']))
+        );
+        assert_eq!(dijkstra('c', neighbors, |&n| n == 'b'), None);
+    }
+
+    #[test]
+    fn test_astar() {
+        let heuristic = |&n: &char| match n {
+            'a' => 2,
+            'b' | 'c' => 1,
+            _ => 0,
+        };
+        assert_eq!(
+            astar('a', neighbors, heuristic, |&n| n == 'd'),
+            Some((2, vec!['a', 'b', 'd']))
+        );
+    }
+
+    #[test]
+    fn test_detect_cycle() {
+        // Keys 0, 1, then 2, 3, 4 repeating, with the total growing by 10 each step
+        let periodic = || {
+            let mut i = 0;
+            move || {
+                let key = if i < 2 { i } else { 2 + (i - 2) % 3 };
+                i += 1;
+                Some((key, i * 10))
+            }
+        };
+        let cycle = detect_cycle(100, periodic()).unwrap();
+        assert_eq!(
+            cycle,
+            CycleInfo {
+                offset: 2,
+                length: 3,
+                gain: 30
+            }
+        );
+        assert_eq!(cycle.steps_taken(), 6);
+        assert_eq!(cycle.skip(10), (90, 1));
+        assert_eq!(detect_cycle(5, periodic()), None);
+
+        // Unkeyed steps still count towards the offset and length
+        let mut i = 0;
+        let cycle = detect_cycle(100, || {
+            i += 1;
+            (i % 2 == 0).then_some((i % 6, i))
+        });
+        assert_eq!(
+            cycle,
+            Some(CycleInfo {
+                offset: 1,
+                length: 6,
+                gain: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_rng() {
+        let sample = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..100).map(|_| rng.range(3..8)).collect::<Vec<_>>()
+        };
+        assert_eq!(sample(1), sample(1));
+        assert_ne!(sample(1), sample(2));
+        assert!(sample(1).iter().all(|n| (3..8).contains(n)));
+        assert_eq!(sample(1).iter().collect::<std::collections::BTreeSet<_>>().len(), 5);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fs, path::Path};
+
+// Known answers, keyed by day, with one entry per part:
+//
+//   1 = [67016, 200116]
+//   25 = ["2=1-=02-21===-21=200"]
+pub(crate) struct Answers(HashMap<u8, Vec<String>>);
+
+impl Answers {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| Self::parse(&s))
+            .map_err(|e| format!("Couldn't read answers from {}: {e}", path.display()))
+    }
+
+    fn parse(input: &str) -> Result<Self, String> {
+        let table = match input.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err("expected a table of answers".into()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut answers = HashMap::new();
+        for (key, value) in table {
+            let day = key
+                .parse::<u8>()
+                .map_err(|_| format!("{key:?} is not a day number"))?;
+            let toml::Value::Array(parts) = value else {
+                return Err(format!("answers for day {day} should be an array"));
+            };
+            let parts = parts
+                .into_iter()
+                .map(|part| match part {
+                    toml::Value::String(s) => Ok(s),
+                    toml::Value::Integer(i) => Ok(i.to_string()),
+                    other => Err(format!("unexpected answer for day {day}: {other}")),
+                })
+                .collect::<Result<_, _>>()?;
+            answers.insert(day, parts);
+        }
+        Ok(Self(answers))
+    }
+
+    fn expected(&self, day: u8, part: u8) -> Option<&str> {
+        let parts = self.0.get(&day)?;
+        parts.get(part as usize - 1).map(|s| s.as_str())
+    }
+}
+
+// Prints PASS/FAIL for the answer, returning false if it didn't match. Days
+// without a known answer are skipped.
+pub(crate) fn check_answer(answers: &Answers, day: u8, part: u8, answer: &str) -> bool {
+    match answers.expected(day, part) {
+        Some(expected) if expected == answer => {
+            println!("PASS day {day} part {part}");
+            true
+        }
+        Some(expected) => {
+            println!("FAIL day {day} part {part}: expected {expected}, got {answer}");
+            false
+        }
+        None => {
+            println!("SKIP day {day} part {part}: no known answer");
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let answers = Answers::parse(
+            r#"
+            1 = [67016, 200116]
+            5 = ["TGWSMRBPN", "TZLTLWRNF"]
+            25 = ["2=1-=02-21===-21=200"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(answers.expected(1, 1), Some("67016"));
+        assert_eq!(answers.expected(1, 2), Some("200116"));
+        assert_eq!(answers.expected(5, 2), Some("TZLTLWRNF"));
+        assert_eq!(answers.expected(25, 1), Some("2=1-=02-21===-21=200"));
+        assert_eq!(answers.expected(25, 2), None);
+        assert_eq!(answers.expected(2, 1), None);
+    }
+
+    #[test]
+    fn test_check_answer() {
+        let answers = Answers::parse("1 = [24000, 45000]").unwrap();
+        assert!(check_answer(&answers, 1, 1, "24000"));
+        assert!(!check_answer(&answers, 1, 2, "24000"));
+        assert!(check_answer(&answers, 2, 1, "15"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Answers::parse("day1 = [1, 2]").is_err());
+        assert!(Answers::parse("1 = 2").is_err());
+        assert!(Answers::parse("1 = [1.5]").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::{env, fmt::Display, fs, io, path::PathBuf};
+
+const SESSION_VAR: &str = "AOC_SESSION";
+
+#[derive(Debug)]
+pub(crate) enum FetchError {
+    InvalidDay(u8),
+    MissingSession,
+    Http(String),
+    Io(io::Error),
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDay(day) => write!(f, "day {day} is not between 1 and 25"),
+            Self::MissingSession => write!(
+                f,
+                "{SESSION_VAR} is not set; copy the session cookie from adventofcode.com into it"
+            ),
+            Self::Http(e) => write!(f, "request failed: {e}"),
+            Self::Io(e) => write!(f, "couldn't write input: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Fetched {
+    Downloaded(PathBuf),
+    AlreadyExists(PathBuf),
+}
+
+fn input_path(day: u8) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "inputs", &format!("{day}.txt")]
+        .iter()
+        .collect()
+}
+
+pub(crate) fn fetch_input(day: u8) -> Result<Fetched, FetchError> {
+    if !(1..=25).contains(&day) {
+        return Err(FetchError::InvalidDay(day));
+    }
+    // Only ever ask the server for an input once
+    let path = input_path(day);
+    if path.exists() {
+        return Ok(Fetched::AlreadyExists(path));
+    }
+    // The session token is only ever placed in the request header; errors from
+    // ureq describe the URL and status, never the headers
+    let session = env::var(SESSION_VAR).map_err(|_| FetchError::MissingSession)?;
+    let input = ureq::get(&format!("https://adventofcode.com/2022/day/{day}/input"))
+        .set("Cookie", &format!("session={session}"))
+        .set("User-Agent", "github.com/rfsmi/adventofcode-2022")
+        .call()
+        .map_err(|e| FetchError::Http(e.to_string()))?
+        .into_string()?;
+    fs::write(&path, input)?;
+    Ok(Fetched::Downloaded(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_input_is_skipped() {
+        assert_eq!(
+            fetch_input(1).unwrap(),
+            Fetched::AlreadyExists(input_path(1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_day() {
+        assert!(matches!(fetch_input(0), Err(FetchError::InvalidDay(0))));
+        assert!(matches!(fetch_input(26), Err(FetchError::InvalidDay(26))));
+    }
+}